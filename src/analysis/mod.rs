@@ -0,0 +1,208 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module looks back at a game and tells the player how well every guess was chosen. We
+//! replay the game with a solver at our side: before every guess we know how many secret numbers
+//! were still possible and what the solver would have guessed, and after it we know how many are
+//! left.
+//!
+//! Information is measured in bits. A guess that leaves half of the numbers gives one bit, one
+//! that leaves a quarter gives two, and so on. The bits a guess gives depend on luck, so to judge a
+//! guess we use the bits it was expected to give: the average over every number that was still
+//! possible. A guess is a mistake if the solver's guess was expected to give more, and the biggest
+//! mistake is the one that has lost the most.
+
+use std::io::{self, Write};
+
+use game::{Game, GameConfig};
+use solver::{Builtin, Solver, SolverError, Strategy, partition};
+
+// A guess that loses less than this is as good as the solver's. Floating point numbers are never
+// quite exact, and nobody cares about a hundredth of a bit.
+const TOLERANCE: f64 = 0.01;
+
+/// What we know about one guess of the game
+pub struct Move {
+    /// The guess as symbol indices
+    pub guess: Vec<u8>,
+    /// The answer to the guess
+    pub cows: u8,
+    /// The answer to the guess
+    pub bulls: u8,
+    /// How many numbers were possible before the guess
+    pub before: usize,
+    /// How many numbers were possible after the answer
+    pub after: usize,
+    /// How many bits the guess was expected to give
+    pub expected: f64,
+    /// What the solver would have guessed instead, or None if it had nothing to say
+    pub best: Option<Vec<u8>>,
+    /// How many bits the solver's guess was expected to give
+    pub best_expected: f64,
+}
+
+impl Move {
+
+    /// How many bits the guess has actually given
+    pub fn gained(&self) -> f64 {
+        bits(self.before, self.after)
+    }
+
+    /// How many bits the guess was expected to lose compared to the solver's guess. The solver
+    /// doesn't always look at every guess, so once in a while the player finds a better one, and
+    /// then nothing is lost.
+    pub fn loss(&self) -> f64 {
+        (self.best_expected - self.expected).max(0.0)
+    }
+}
+
+/// The analysis of a whole game
+pub struct Report {
+    /// The rules of the game
+    pub config: GameConfig,
+    /// The name of the strategy the guesses were compared with
+    pub strategy: String,
+    /// Every guess of the game
+    pub moves: Vec<Move>,
+}
+
+impl Report {
+
+    /// True if the last guess has found the number
+    pub fn solved(&self) -> bool {
+        self.moves.last().is_some_and(|last| last.bulls as usize == self.config.length())
+    }
+
+    /// The index of the guess that has lost the most, or None if every guess was as good as the
+    /// solver's
+    pub fn biggest_mistake(&self) -> Option<usize> {
+        self.moves.iter()
+            .enumerate()
+            .filter(|&(_, step)| step.loss() > TOLERANCE)
+            .max_by(|a, b| a.1.loss().total_cmp(&b.1.loss()))
+            .map(|(index, _)| index)
+    }
+
+    /// Writes the report as a table of text for the terminal
+    pub fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let width = self.config.length().max(5);
+        writeln!(out, "{}", self.summary())?;
+        writeln!(out, "  # {:<w$} C B  before  after  bits  expected  {:<w$} expected",
+                 "guess", "solver", w = width)?;
+        for (i, step) in self.moves.iter().enumerate() {
+            writeln!(out, "{:>3} {:<w$} {} {} {:>7} {:>6} {:>5.2} {:>9.2}  {:<w$} {:>8.2}",
+                     i + 1, self.config.format_code(&step.guess), step.cows, step.bulls,
+                     step.before, step.after, step.gained(), step.expected,
+                     self.best_name(step), step.best_expected, w = width)?;
+        }
+        writeln!(out, "{}", self.verdict())
+    }
+
+    /// Writes the report as a Markdown document, to keep or to share
+    pub fn write_markdown<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "# Cows and Bulls game report")?;
+        writeln!(out)?;
+        writeln!(out, "{}", self.summary())?;
+        writeln!(out)?;
+        writeln!(out, "| # | Guess | Cows | Bulls | Numbers before | Numbers after | Bits gained | Bits expected | Solver's guess | Solver's bits expected |")?;
+        writeln!(out, "|--:|:------|-----:|------:|---------------:|--------------:|------------:|--------------:|:---------------|-----------------------:|")?;
+        for (i, step) in self.moves.iter().enumerate() {
+            // The biggest mistake is written in bold, so it's easy to find
+            let mark = if self.biggest_mistake() == Some(i) { "**" } else { "" };
+            writeln!(out, "| {} | {m}{}{m} | {} | {} | {} | {} | {:.2} | {m}{:.2}{m} | {} | {:.2} |",
+                     i + 1, self.config.format_code(&step.guess), step.cows, step.bulls,
+                     step.before, step.after, step.gained(), step.expected,
+                     self.best_name(step), step.best_expected, m = mark)?;
+        }
+        writeln!(out)?;
+        writeln!(out, "{}", self.verdict())
+    }
+
+    // A line about the rules and how the game went
+    fn summary(&self) -> String {
+        let result = if self.solved() {
+            format!("solved in {} tries", self.moves.len())
+        } else {
+            format!("not solved, {} tries so far", self.moves.len())
+        };
+        format!("Game of {}, {}. Compared with the {} strategy.", self.config.describe(), result, self.strategy)
+    }
+
+    // A line about the biggest mistake
+    fn verdict(&self) -> String {
+        match self.biggest_mistake() {
+            Some(index) => {
+                let step = &self.moves[index];
+                format!("The biggest mistake was guess {} ({}): it was expected to give {:.2} bits, but {} would give {:.2}.",
+                        index + 1, self.config.format_code(&step.guess), step.expected,
+                        self.best_name(step), step.best_expected)
+            },
+            None => "Every guess was as good as the solver's. Well played!".to_string(),
+        }
+    }
+
+    fn best_name(&self, step: &Move) -> String {
+        match step.best {
+            Some(ref best) => self.config.format_code(best),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// Analyzes a game, comparing it with the entropy strategy, which picks the guess that is
+/// expected to give the most bits
+pub fn analyze(game: &Game) -> Result<Report, SolverError> {
+    analyze_with(game, &Builtin::Entropy)
+}
+
+/// Analyzes a game, comparing it with the given strategy. In hard mode the strategy follows the
+/// rule of hard mode too, so it's only compared with guesses the player could make.
+pub fn analyze_with(game: &Game, strategy: &dyn Strategy) -> Result<Report, SolverError> {
+    let config = &game.config;
+    let mut solver = Solver::new(config, strategy)?;
+    solver.set_hard(game.hard);
+
+    let mut moves = Vec::new();
+    for turn in &game.history {
+        let before = solver.candidates().len();
+        let best = solver.next_guess();
+        let expected = expected_bits(config, solver.candidates(), &turn.guess);
+        let best_expected = best.as_ref().map(|best| expected_bits(config, solver.candidates(), best)).unwrap_or(0.0);
+
+        solver.record(&turn.guess, turn.cows, turn.bulls);
+        moves.push(Move {
+            guess: turn.guess.clone(),
+            cows: turn.cows,
+            bulls: turn.bulls,
+            before,
+            after: solver.candidates().len(),
+            expected,
+            best,
+            best_expected,
+        });
+    }
+
+    Ok(Report { config: config.clone(), strategy: strategy.name().to_string(), moves })
+}
+
+// The bits we get when the possible numbers go from one count to another. If nothing is left,
+// somebody has made a mistake, and there's nothing to measure.
+fn bits(before: usize, after: usize) -> f64 {
+    if after == 0 {
+        0.0
+    } else {
+        (before as f64 / after as f64).log2()
+    }
+}
+
+// The bits a guess is expected to give. Every answer has a chance to come, which is the share of
+// the numbers that give it, and we average the bits of every answer with these chances.
+fn expected_bits(config: &GameConfig, candidates: &[Vec<u8>], guess: &[u8]) -> f64 {
+    let total = candidates.len();
+    partition(config, candidates, guess).iter()
+        .filter(|&&size| size > 0)
+        .map(|&size| size as f64 / total as f64 * bits(total, size))
+        .sum()
+}
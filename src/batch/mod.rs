@@ -0,0 +1,104 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// This module plays a game without talking to a human. Guesses are read one per line from the
+// standard input or a file, and every answer is printed as a single line of the guess, the cows
+// and the bulls, like "1234 1 2". There are no prompts, no hints and no auto-save, so the output
+// is easy to read for another program. How the game has ended is told by the exit code of the
+// program, which is what scripts usually look at.
+
+use std::io::BufRead;
+
+use cowbull::game::{Game, Guess};
+
+// Exit codes of the batch mode. Zero means success for the shell, so it's the win.
+pub const EXIT_WIN: i32 = 0;
+pub const EXIT_GIVE_UP: i32 = 1;
+pub const EXIT_INVALID: i32 = 2;
+
+// Plays the game with guesses from the input and returns the exit code. Empty lines and lines
+// starting with # are skipped, so recorded games can have comments. The game is over with the
+// first winning guess, the rest of the input is not read.
+pub fn play<R: BufRead>(game: &mut Game, input: R) -> i32 {
+    for (i, line) in input.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                eprintln!("Can't read the input: {}", error);
+                return EXIT_INVALID;
+            },
+        };
+        let line = line.trim();
+
+        match line {
+            "" => continue,
+            _ if line.starts_with('#') => continue,
+            // A recorded game can give up on purpose
+            "q" | "quit" | "exit" => return EXIT_GIVE_UP,
+            _ => (),
+        }
+
+        // Errors go to the standard error stream, so they don't mix with the answers. The line
+        // number helps to find the bad guess in a long file.
+        let guess = match Guess::parse(line, &game.config) {
+            Ok(guess) => guess,
+            Err(error) => {
+                eprintln!("Line {}: {}", i + 1, error);
+                return EXIT_INVALID;
+            },
+        };
+        if let Err(contradiction) = game.check_hard(&guess) {
+            eprintln!("Line {}: {}", i + 1, contradiction);
+            return EXIT_INVALID;
+        }
+
+        let code = game.config.format_code(guess.code());
+        let (cows, bulls, won) = game.play(&guess);
+        println!("{} {} {}", code, cows, bulls);
+        if won {
+            return EXIT_WIN;
+        }
+    }
+
+    // The input is over and the number wasn't guessed
+    EXIT_GIVE_UP
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EXIT_GIVE_UP, EXIT_INVALID, EXIT_WIN, play};
+    use cowbull::game::{Game, GameConfig};
+
+    // Plays the lines against a game of 1234 and returns the exit code and the number of tries
+    fn run(input: &str, hard: bool) -> (i32, u32) {
+        let mut game = Game::with_secret(GameConfig::default(), &"1234".parse().unwrap());
+        game.hard = hard;
+        let code = play(&mut game, input.as_bytes());
+        (code, game.tries)
+    }
+
+    #[test]
+    fn win() {
+        assert_eq!(run("5678\n1234\n", false), (EXIT_WIN, 2));
+        // Comments and empty lines are skipped, and nothing after the win is read
+        assert_eq!(run("# a comment\n\n  1234  \nbad\n", false), (EXIT_WIN, 1));
+    }
+
+    #[test]
+    fn give_up() {
+        assert_eq!(run("", false), (EXIT_GIVE_UP, 0));
+        assert_eq!(run("5678\n4321", false), (EXIT_GIVE_UP, 2));
+        assert_eq!(run("5678\nquit\n1234\n", false), (EXIT_GIVE_UP, 1));
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(run("5678\n12a4\n1234\n", false), (EXIT_INVALID, 1));
+        assert_eq!(run("1123\n", false), (EXIT_INVALID, 0));
+        // 1256 scores two bulls, so 7890 breaks the rule of hard mode, but only there
+        assert_eq!(run("1256\n7890\n", true), (EXIT_INVALID, 1));
+        assert_eq!(run("1256\n7890\n", false), (EXIT_GIVE_UP, 2));
+    }
+}
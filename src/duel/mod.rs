@@ -0,0 +1,346 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// This module plays the game the way it's described in the README: two humans make up secret
+// numbers for each other and take turns guessing. Each of them plays an ordinary game against the
+// secret of the other one, so scoring is the same as in the game with the computer.
+//
+// The first player to guess the number wins, but the first player has also made the first move.
+// To make it fair, when the first player wins, the second one gets one last turn to equalize, and
+// if it's a win too, the duel is a draw.
+//
+// The players can sit at the same terminal and pass the keyboard to each other, or play over the
+// network (see the server module). The rules of the duel don't care which, they talk to the
+// players through the Table trait below.
+
+use std::io::{self, stdin, stdout, BufRead, Write};
+use std::process::{Command, Stdio};
+
+use cowbull::game::{Contradiction, Game, GameConfig, Guess};
+
+use protocol;
+
+// How a duel stands after a guess
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Outcome {
+    // Nobody has won yet
+    Playing,
+    // The first player has guessed the number and the second one has one last turn
+    LastTurn,
+    // The player with this index has won
+    Won(usize),
+    // Both players have guessed their numbers in the same number of turns
+    Draw,
+}
+
+// The state of a duel: two games and whose turn it is
+pub struct Duel {
+    // The game of every player. The secret of a player's game was made up by the other player.
+    games: Vec<Game>,
+    turn: usize,
+    outcome: Outcome,
+}
+
+impl Duel {
+
+    // Starts a duel. The secrets are in the order of the players who made them up.
+    pub fn new(config: &GameConfig, secrets: &[Guess; 2], hard: bool) -> Duel {
+        let games = (0..2)
+            .map(|player| {
+                let mut game = Game::with_secret(config.clone(), &secrets[1 - player]);
+                game.hard = hard;
+                game
+            })
+            .collect();
+        Duel { games, turn: 0, outcome: Outcome::Playing }
+    }
+
+    // The index of the player who guesses next
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    // The game of a player
+    pub fn game(&self, player: usize) -> &Game {
+        &self.games[player]
+    }
+
+    // Scores the guess of the player whose turn it is and passes the turn to the other one.
+    // In hard mode a guess that contradicts an earlier answer doesn't count, and the turn stays.
+    pub fn play(&mut self, guess: &Guess) -> Result<(u8, u8, Outcome), Contradiction> {
+        let player = self.turn;
+        let game = &mut self.games[player];
+        game.check_hard(guess)?;
+
+        // Every guess is played the same way, so the winning one is counted and remembered too
+        let (cows, bulls, won) = game.play(guess);
+        game.analyze();
+
+        self.outcome = match (player, won, self.outcome) {
+            (0, true, _) => Outcome::LastTurn,
+            (1, true, Outcome::LastTurn) => Outcome::Draw,
+            (1, true, _) => Outcome::Won(1),
+            (1, false, Outcome::LastTurn) => Outcome::Won(0),
+            (_, _, outcome) => outcome,
+        };
+        self.turn = 1 - player;
+        Ok((cows, bulls, self.outcome))
+    }
+}
+
+// The way a duel talks to its players. Both players can share one terminal or have their own
+// connections, and the duel doesn't need to know which.
+pub trait Table {
+    // Asks a player for a line of input and returns None if the player has left. A hidden line
+    // is not shown to anybody while it's typed.
+    fn ask(&mut self, player: usize, prompt: &str, hidden: bool) -> io::Result<Option<String>>;
+    // Tells something to one player
+    fn tell(&mut self, player: usize, message: &str) -> io::Result<()>;
+    // Tells something to both players
+    fn announce(&mut self, message: &str) -> io::Result<()>;
+}
+
+// Plays a duel at a table until somebody wins or leaves
+pub fn play<T: Table>(table: &mut T, config: &GameConfig, hard: bool) -> io::Result<()> {
+    table.announce(&format!("Duel! Both players make up a number of {} ({}) for each other",
+                            config.describe(), config.symbols().iter().collect::<String>()))?;
+    if hard {
+        table.announce("Hard mode: every guess must agree with all earlier answers")?;
+    }
+
+    // Every player makes up a secret for the other one, first player first
+    let mut secrets = Vec::new();
+    for player in 0..2 {
+        table.tell(1 - player, &format!("{} is making up a number", name(player)))?;
+        match read_secret(table, player, config)? {
+            Some(secret) => secrets.push(secret),
+            None => return table.announce(&format!("{} has left", name(player))),
+        }
+    }
+    let secrets = [secrets[0].clone(), secrets[1].clone()];
+    let mut duel = Duel::new(config, &secrets, hard);
+    table.announce("Both numbers are ready, let's go!")?;
+
+    loop {
+        let player = duel.turn();
+        let prompt = format!("Guess {} ('h' for help)", duel.game(player).tries + 1);
+        let input = match table.ask(player, &prompt, false)? {
+            Some(input) => input,
+            None => return table.announce(&format!("{} has left, {} wins!", name(player), name(1 - player))),
+        };
+
+        match input.trim() {
+            "" => continue,
+            "q" | "quit" | "exit" =>
+                return table.announce(&format!("{} gives up, {} wins!", name(player), name(1 - player))),
+            "h" | "help" | "?" => {
+                table.tell(player, "<guess>       - Guess the number of the other player")?;
+                table.tell(player, "s, hints      - Check out hints on symbol positions")?;
+                table.tell(player, "history       - List your guesses with their answers")?;
+                table.tell(player, "q, quit, exit - Give up")?;
+            },
+            "s" | "hints" => for line in ::hint_lines(duel.game(player)) {
+                table.tell(player, &line)?;
+            },
+            "history" => for line in ::history_lines(duel.game(player)) {
+                table.tell(player, &line)?;
+            },
+            input => {
+                let guess = match Guess::parse(input, config) {
+                    Ok(guess) => guess,
+                    Err(error) => {
+                        table.tell(player, &error.to_string())?;
+                        continue;
+                    },
+                };
+                let (cows, bulls, outcome) = match duel.play(&guess) {
+                    Ok(result) => result,
+                    Err(contradiction) => {
+                        table.tell(player, &contradiction.to_string())?;
+                        continue;
+                    },
+                };
+
+                table.announce(&format!("{} guesses {}: {} cows and {} bulls",
+                                        name(player), config.format_code(guess.code()), cows, bulls))?;
+                match outcome {
+                    Outcome::Playing => (),
+                    Outcome::LastTurn => table.announce(&format!(
+                        "{} has guessed the number! {} has one last turn to equalize", name(0), name(1)))?,
+                    Outcome::Won(winner) => {
+                        table.announce(&format!("{} wins in {} tries!", name(winner), duel.game(winner).tries))?;
+                        return reveal(table, config, &secrets);
+                    },
+                    Outcome::Draw => {
+                        table.announce(&format!("Both players have guessed the numbers in {} tries, it's a draw!",
+                                                duel.game(0).tries))?;
+                        return reveal(table, config, &secrets);
+                    },
+                }
+            },
+        }
+    }
+}
+
+// Asks a player for a secret number until it's valid for the rules, or None if the player leaves
+fn read_secret<T: Table>(table: &mut T, player: usize, config: &GameConfig) -> io::Result<Option<Guess>> {
+    loop {
+        let input = match table.ask(player, "Make up your secret number", true)? {
+            Some(input) => input,
+            None => return Ok(None),
+        };
+        match Guess::parse(input.trim(), config) {
+            Ok(secret) => return Ok(Some(secret)),
+            Err(error) => table.tell(player, &error.to_string())?,
+        }
+    }
+}
+
+// Shows both secret numbers when the duel is over
+fn reveal<T: Table>(table: &mut T, config: &GameConfig, secrets: &[Guess; 2]) -> io::Result<()> {
+    table.announce(&format!("The numbers were {} by {} and {} by {}",
+                            config.format_code(secrets[0].code()), name(0),
+                            config.format_code(secrets[1].code()), name(1)))
+}
+
+// Players are numbered from zero in the code and from one for humans
+pub fn name(player: usize) -> String {
+    format!("Player {}", player + 1)
+}
+
+// Both players share one terminal and pass the keyboard to each other, so every prompt tells
+// whose turn it is and everything is shown to both of them
+pub struct HotSeat;
+
+impl Table for HotSeat {
+    fn ask(&mut self, player: usize, prompt: &str, hidden: bool) -> io::Result<Option<String>> {
+        print!("{}: {} > ", name(player), prompt);
+        stdout().flush()?;
+
+        // The stty program tells the terminal to stop showing what's typed. If the input is not
+        // a terminal, it fails and there's nothing to hide anyway.
+        let hidden = hidden && echo(false);
+
+        let mut input = String::new();
+        let read = stdin().read_line(&mut input);
+
+        // The Enter key wasn't shown either, so we move to the next line ourselves
+        if hidden {
+            echo(true);
+            println!();
+        }
+
+        match read? {
+            0 => Ok(None),
+            _ => Ok(Some(input)),
+        }
+    }
+
+    fn tell(&mut self, _player: usize, message: &str) -> io::Result<()> {
+        println!("{}", message);
+        Ok(())
+    }
+
+    fn announce(&mut self, message: &str) -> io::Result<()> {
+        println!("{}", message);
+        Ok(())
+    }
+}
+
+// Turns showing the typed characters on or off. Returns true if it has worked. The complaints of
+// stty go nowhere, we don't want them to spoil the screen.
+fn echo(on: bool) -> bool {
+    Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+// Each player has a connection of their own, like in the server. The input and output types are
+// generic, so anything that can be read line by line and written to will do.
+pub struct Remote<R: BufRead, W: Write> {
+    pub players: Vec<(R, W)>,
+}
+
+impl<R: BufRead, W: Write> Table for Remote<R, W> {
+    // There's nothing to hide, the other player can't see what's typed on another computer
+    fn ask(&mut self, player: usize, prompt: &str, _hidden: bool) -> io::Result<Option<String>> {
+        let (ref mut input, ref mut output) = self.players[player];
+        write!(output, "{} > ", prompt)?;
+        output.flush()?;
+
+        protocol::read_line(input)
+    }
+
+    fn tell(&mut self, player: usize, message: &str) -> io::Result<()> {
+        let output = &mut self.players[player].1;
+        writeln!(output, "{}", message)?;
+        output.flush()
+    }
+
+    // A player who has left can't hear us, but the other one still can, so we tell both players
+    // before giving up on an error
+    fn announce(&mut self, message: &str) -> io::Result<()> {
+        let first = self.tell(0, message);
+        let second = self.tell(1, message);
+        first.and(second)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Duel;
+    use super::Outcome::*;
+    use cowbull::game::{GameConfig, Guess};
+
+    // The first player makes up 1234 and the second one 5678, so the first one has to guess 5678
+    fn duel(hard: bool) -> Duel {
+        Duel::new(&GameConfig::default(), &["1234".parse().unwrap(), "5678".parse().unwrap()], hard)
+    }
+
+    fn play(duel: &mut Duel, guess: &str) -> (u8, u8, super::Outcome) {
+        duel.play(&guess.parse::<Guess>().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn second_player_wins() {
+        let mut duel = duel(false);
+        assert_eq!(play(&mut duel, "1234"), (0, 0, Playing));
+        assert_eq!(duel.turn(), 1);
+        assert_eq!(play(&mut duel, "1243"), (2, 2, Playing));
+        assert_eq!(play(&mut duel, "8765"), (4, 0, Playing));
+        assert_eq!(play(&mut duel, "1234"), (0, 4, Won(1)));
+        assert_eq!(duel.game(1).tries, 2);
+    }
+
+    // The first player's win only counts when the second one misses the last turn
+    #[test]
+    fn last_turn() {
+        let mut duel = duel(false);
+        assert_eq!(play(&mut duel, "5678"), (0, 4, LastTurn));
+        assert_eq!(duel.turn(), 1);
+        assert_eq!(play(&mut duel, "4321"), (4, 0, Won(0)));
+    }
+
+    #[test]
+    fn draw() {
+        let mut duel = duel(false);
+        assert_eq!(play(&mut duel, "5678"), (0, 4, LastTurn));
+        assert_eq!(play(&mut duel, "1234"), (0, 4, Draw));
+    }
+
+    // A guess that breaks the rule of hard mode doesn't count, and the player guesses again
+    #[test]
+    fn hard_mode() {
+        let mut duel = duel(true);
+        play(&mut duel, "5690");
+        play(&mut duel, "1234");
+        assert!(duel.play(&"1234".parse().unwrap()).is_err());
+        assert_eq!((duel.turn(), duel.game(0).tries), (0, 1));
+        assert_eq!(play(&mut duel, "5678"), (0, 4, LastTurn));
+    }
+}
@@ -0,0 +1,311 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module describes the rules of a single game: how long the secret code is and which
+//! symbols it can be made of. The classic game is four unique decimal digits, but the same engine
+//! can play three-digit practice games or hard six-symbol hexadecimal ones.
+
+use std::fmt;
+
+// Bounds for the code length. Anything shorter than three is not much of a puzzle and ten is the
+// longest code that still fits into the decimal alphabet with unique digits.
+
+/// The shortest code length
+pub const MIN_LENGTH: usize = 3;
+/// The longest code length
+pub const MAX_LENGTH: usize = 10;
+
+/// The most symbols an alphabet can have. The game keeps a symbol as its index in the alphabet,
+/// and the index is a u8, which goes from 0 to 255.
+pub const MAX_SYMBOLS: usize = 256;
+
+/// The set of symbols the secret code is made of. The first three variants are presets and the
+/// last one carries a list of symbols chosen by the player. Debug trait is derived so we can print
+/// the value while debugging.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Alphabet {
+    /// Decimal digits from 0 to 9
+    Digits,
+    /// Hexadecimal digits from 0 to f
+    Hex,
+    /// Lowercase latin letters from a to z
+    Letters,
+    /// Any other set of symbols
+    Custom(Vec<char>),
+}
+
+impl Alphabet {
+
+    /// Turns a name from the command line into an alphabet. Known names give us a preset, anything
+    /// else is treated as a list of custom symbols, so "--alphabet abcdef" just works.
+    pub fn from_name(name: &str) -> Alphabet {
+        match name {
+            "digits" | "dec" => Alphabet::Digits,
+            "hex" => Alphabet::Hex,
+            "letters" | "abc" => Alphabet::Letters,
+            _ => Alphabet::Custom(name.chars().collect()),
+        }
+    }
+
+    /// The name of a preset, or None for custom symbols. from_name() turns it back into the preset.
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            Alphabet::Digits => Some("digits"),
+            Alphabet::Hex => Some("hex"),
+            Alphabet::Letters => Some("letters"),
+            Alphabet::Custom(_) => None,
+        }
+    }
+
+    /// Returns the list of symbols of this alphabet in their natural order
+    pub fn symbols(&self) -> Vec<char> {
+        match *self {
+            Alphabet::Digits => "0123456789".chars().collect(),
+            Alphabet::Hex => "0123456789abcdef".chars().collect(),
+            Alphabet::Letters => "abcdefghijklmnopqrstuvwxyz".chars().collect(),
+            // Custom symbols are already a vector, so we just clone it
+            Alphabet::Custom(ref symbols) => symbols.clone(),
+        }
+    }
+}
+
+/// Things that can go wrong when we build a configuration out of user input
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum ConfigError {
+    /// Code length is outside of MIN_LENGTH..MAX_LENGTH range
+    LengthOutOfRange(usize),
+    /// There are fewer symbols than positions, so unique symbols can't fill the code
+    NotEnoughSymbols(usize, usize),
+    /// A game with repeats needs at least two symbols, otherwise there's nothing to guess
+    TooFewSymbols(usize),
+    /// The alphabet has more than MAX_SYMBOLS symbols
+    TooManySymbols(usize),
+    /// The same symbol is mentioned twice in a custom alphabet
+    DuplicateSymbol(char),
+    /// Whitespace can't be a symbol, because we trim the input
+    BadSymbol(char),
+}
+
+// Implementing the Display trait lets us print errors with println!("{}", error)
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::LengthOutOfRange(length) =>
+                write!(f, "Code length must be from {} to {}, got {}", MIN_LENGTH, MAX_LENGTH, length),
+            ConfigError::NotEnoughSymbols(length, symbols) =>
+                write!(f, "Code of {} unique symbols needs at least {} symbols, got {}", length, length, symbols),
+            ConfigError::TooFewSymbols(symbols) =>
+                write!(f, "At least 2 symbols are needed, got {}", symbols),
+            ConfigError::TooManySymbols(symbols) =>
+                write!(f, "At most {} symbols can be used, got {}", MAX_SYMBOLS, symbols),
+            ConfigError::DuplicateSymbol(symbol) =>
+                write!(f, "Symbol '{}' is mentioned twice in the alphabet", symbol),
+            ConfigError::BadSymbol(symbol) =>
+                write!(f, "Symbol '{:?}' can't be used in the alphabet", symbol),
+        }
+    }
+}
+
+/// The rules of a game. Fields are private, so the only way to get a configuration is through the
+/// constructor below that checks that these rules make sense.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct GameConfig {
+    // How many positions the secret code has
+    length: usize,
+    // Which alphabet is used, so we can tell digits from letters in messages
+    alphabet: Alphabet,
+    // We cache the list of symbols, so we don't build it again on every lookup
+    symbols: Vec<char>,
+    // When true, a symbol can be used more than once in the code, like in Mastermind
+    repeats: bool,
+}
+
+impl GameConfig {
+
+    /// Constructor that validates the rules of the classic game, where all symbols of the code are
+    /// unique. It returns a Result, so a caller has to deal with a configuration that can't be
+    /// played.
+    pub fn new(length: usize, alphabet: Alphabet) -> Result<GameConfig, ConfigError> {
+        GameConfig::with_repeats(length, alphabet, false)
+    }
+
+    /// Same as new(), but can also allow repeated symbols in the secret code and in the guesses.
+    /// Six symbols on four positions with repeats is the real Mastermind.
+    pub fn with_repeats(length: usize, alphabet: Alphabet, repeats: bool) -> Result<GameConfig, ConfigError> {
+        if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) {
+            return Err(ConfigError::LengthOutOfRange(length));
+        }
+
+        let symbols = alphabet.symbols();
+        if symbols.len() > MAX_SYMBOLS {
+            return Err(ConfigError::TooManySymbols(symbols.len()));
+        }
+
+        // Check every symbol against the symbols that come after it to find duplicates
+        for (i, symbol) in symbols.iter().enumerate() {
+            if symbol.is_whitespace() {
+                return Err(ConfigError::BadSymbol(*symbol));
+            }
+            if symbols[(i + 1)..].contains(symbol) {
+                return Err(ConfigError::DuplicateSymbol(*symbol));
+            }
+        }
+
+        // Unique symbols need enough of them to fill the code. Repeated symbols can fill any code,
+        // but a single symbol makes a game with one possible answer.
+        if repeats && symbols.len() < 2 {
+            return Err(ConfigError::TooFewSymbols(symbols.len()));
+        }
+        if !repeats && symbols.len() < length {
+            return Err(ConfigError::NotEnoughSymbols(length, symbols.len()));
+        }
+
+        Ok(GameConfig {
+            length,
+            alphabet,
+            symbols,
+            repeats,
+        })
+    }
+
+    /// Number of positions in the secret code
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The alphabet these rules were built with
+    pub fn alphabet(&self) -> &Alphabet {
+        &self.alphabet
+    }
+
+    /// Whether a symbol can be used more than once in a code
+    pub fn repeats(&self) -> bool {
+        self.repeats
+    }
+
+    /// All symbols that can be used in the code. Internally the game stores symbols as their
+    /// indices in this list, so digit 7 is index 7 and letter 'c' is index 2.
+    pub fn symbols(&self) -> &[char] {
+        &self.symbols
+    }
+
+    /// Returns the symbol for an internal index
+    pub fn symbol(&self, index: u8) -> char {
+        self.symbols[index as usize]
+    }
+
+    /// Returns the internal index of a symbol or None if the symbol is not in the alphabet
+    pub fn index_of(&self, symbol: char) -> Option<u8> {
+        self.symbols.iter().position(|&s| s == symbol).map(|i| i as u8)
+    }
+
+    /// Turns a vector of symbol indices back into a string the player can read
+    pub fn format_code(&self, code: &[u8]) -> String {
+        code.iter().map(|&index| self.symbol(index)).collect()
+    }
+
+    /// Checks that a code of symbol indices fits these rules: it has the right length, every index
+    /// is in the alphabet and, unless repeats are allowed, no symbol is used twice
+    pub fn is_valid_code(&self, code: &[u8]) -> bool {
+        code.len() == self.length
+            && code.iter().all(|&index| (index as usize) < self.symbols.len())
+            && (self.repeats || code.iter().enumerate().all(|(i, index)| !code[(i + 1)..].contains(index)))
+    }
+
+    /// Number of different codes these rules allow. The first position can hold any symbol, the
+    /// second one any symbol but the first, and so on. With repeats every position can hold any
+    /// symbol. We use u64, because 26 letters on 10 positions give a really big number. Even u64
+    /// is not enough for 256 symbols on 10 positions, so the checked methods tell us when the
+    /// number doesn't fit, and then we give u64::MAX, which is way too many codes for anything
+    /// anyway.
+    pub fn space_size(&self) -> u64 {
+        let symbols = self.symbols.len() as u64;
+        let size = if self.repeats {
+            symbols.checked_pow(self.length as u32)
+        } else {
+            (0..self.length as u64).try_fold(1u64, |size, i| size.checked_mul(symbols - i))
+        };
+        size.unwrap_or(u64::MAX)
+    }
+
+    /// A word to call our symbols in messages to the player
+    pub fn symbol_name(&self) -> &'static str {
+        match self.alphabet {
+            Alphabet::Digits | Alphabet::Hex => "digits",
+            Alphabet::Letters => "letters",
+            Alphabet::Custom(_) => "symbols",
+        }
+    }
+
+    /// Describes the secret code in messages to the player, like "4 unique digits"
+    pub fn describe(&self) -> String {
+        if self.repeats {
+            format!("{} {} (repeats allowed)", self.length, self.symbol_name())
+        } else {
+            format!("{} unique {}", self.length, self.symbol_name())
+        }
+    }
+}
+
+// The Default trait gives us the classic rules: four unique decimal digits
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig::new(4, Alphabet::Digits).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Alphabet, ConfigError, GameConfig, MAX_SYMBOLS};
+    use super::ConfigError::*;
+
+    fn custom(symbols: &str) -> Alphabet {
+        Alphabet::Custom(symbols.chars().collect())
+    }
+
+    // An alphabet of any size, made of letters that no preset has
+    fn many(count: usize) -> Alphabet {
+        Alphabet::Custom((0..count as u32).map(|i| char::from_u32(0x100 + i).unwrap()).collect())
+    }
+
+    fn check(length: usize, alphabet: Alphabet, repeats: bool) -> Result<(), ConfigError> {
+        GameConfig::with_repeats(length, alphabet, repeats).map(|_| ())
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        assert_eq!(check(2, Alphabet::Digits, false), Err(LengthOutOfRange(2)));
+        assert_eq!(check(11, Alphabet::Letters, true), Err(LengthOutOfRange(11)));
+        assert_eq!(check(4, custom("abc"), false), Err(NotEnoughSymbols(4, 3)));
+        assert_eq!(check(4, custom("a"), true), Err(TooFewSymbols(1)));
+        assert_eq!(check(3, custom("abca"), false), Err(DuplicateSymbol('a')));
+        assert_eq!(check(3, custom("ab c"), false), Err(BadSymbol(' ')));
+
+        // Symbol indices are u8, so there can't be more than 256 of them
+        assert_eq!(check(4, many(MAX_SYMBOLS + 1), false), Err(TooManySymbols(MAX_SYMBOLS + 1)));
+    }
+
+    #[test]
+    fn good_rules_are_accepted() {
+        assert_eq!(check(3, custom("abc"), false), Ok(()));
+        assert_eq!(check(4, custom("ab"), true), Ok(()));
+        assert_eq!(check(10, Alphabet::Digits, false), Ok(()));
+        assert_eq!(check(4, many(MAX_SYMBOLS), false), Ok(()));
+    }
+
+    #[test]
+    fn space_size() {
+        let size = |length, alphabet, repeats| {
+            GameConfig::with_repeats(length, alphabet, repeats).unwrap().space_size()
+        };
+        assert_eq!(size(4, Alphabet::Digits, false), 10 * 9 * 8 * 7);
+        assert_eq!(size(4, custom("abcdef"), true), 6 * 6 * 6 * 6);
+        assert_eq!(size(10, Alphabet::Digits, false), 3_628_800);
+
+        // 256 symbols on 10 positions don't fit into u64, with repeats or without
+        assert_eq!(size(10, many(MAX_SYMBOLS), false), u64::MAX);
+        assert_eq!(size(10, many(MAX_SYMBOLS), true), u64::MAX);
+    }
+}
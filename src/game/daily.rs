@@ -0,0 +1,84 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module gives everybody the same puzzle on the same day. The seed of the secret number is
+//! made from the current date in UTC, so it doesn't matter where in the world you play: until
+//! midnight in Greenwich everyone gets the same number and can compare their tries.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The number of seconds in a day. Unix time pretends that leap seconds don't exist, so every day
+// is exactly this long.
+const DAY: u64 = 24 * 60 * 60;
+
+/// Returns the number of days since the 1st of January 1970 in UTC. Times before that are not
+/// worth the trouble, so they count as day zero.
+pub fn day_number(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs() / DAY).unwrap_or(0)
+}
+
+/// Makes the seed of the daily puzzle. The day number alone would make puzzles of neighbouring days
+/// start from neighbouring seeds, so we stir it a bit. Any fixed mix will do, as long as it never
+/// changes, otherwise yesterday's puzzle would change too.
+pub fn daily_seed(time: SystemTime) -> u64 {
+    day_number(time).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ 0x00c0_ffee
+}
+
+/// Turns the day number into a calendar date: year, month and day. This is a well-known algorithm
+/// by Howard Hinnant. It shifts the year to start in March, so the leap day is the last day of the
+/// year, and then counts whole 400-year eras, years and months.
+pub fn date(day: u64) -> (u64, u32, u32) {
+    let days = day + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day_of_month)
+}
+
+/// Formats a moment of time as a date in UTC, like 2015-06-27
+pub fn format_date(time: SystemTime) -> String {
+    let (year, month, day) = date(day_number(time));
+    format!("{}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{DAY, daily_seed, date, day_number, format_date};
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(0), (1970, 1, 1));
+        assert_eq!(date(59), (1970, 3, 1));
+        // 2000 was a leap year, even though it's divisible by 100
+        assert_eq!(date(11_016), (2000, 2, 29));
+        assert_eq!(date(11_017), (2000, 3, 1));
+        assert_eq!(date(16_616), (2015, 6, 30));
+        assert_eq!(date(16_800), (2015, 12, 31));
+        assert_eq!(date(16_801), (2016, 1, 1));
+    }
+
+    // The seed changes at midnight in UTC and not a second earlier
+    #[test]
+    fn same_day_same_seed() {
+        let midnight = UNIX_EPOCH + Duration::from_secs(16_616 * DAY);
+        let evening = midnight + Duration::from_secs(DAY - 1);
+        assert_eq!(day_number(evening), 16_616);
+        assert_eq!(daily_seed(midnight), daily_seed(evening));
+        assert_ne!(daily_seed(evening), daily_seed(evening + Duration::from_secs(1)));
+        assert_eq!(format_date(evening), "2015-06-30");
+    }
+
+    #[test]
+    fn before_the_epoch() {
+        assert_eq!(day_number(UNIX_EPOCH - Duration::from_secs(DAY * 3)), 0);
+    }
+}
@@ -0,0 +1,118 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! The evil host cheats, but in a way nobody can prove. It doesn't make up a secret number at the
+//! start. Instead it keeps every number the rules allow, and after each guess it gives the answer
+//! that leaves as many of them as possible. All its answers always agree with each other, so there
+//! is a secret number that would give every one of them, but the player has to corner the host to
+//! win. This is the worst luck a player can have, which is useful to test how good a player or
+//! a bot is in the worst case.
+
+use super::{Game, GameConfig, Secret, score};
+use solver::{SolverError, all_codes};
+
+impl Game {
+
+    /// Creates a game with the evil host. The host needs to keep every possible number in
+    /// memory, so it refuses rules with too many of them, same as the solver.
+    pub fn evil(config: GameConfig) -> Result<Game, SolverError> {
+        let numbers = all_codes(&config)?;
+
+        // Everything else is the same as in a normal game, so we make one and swap the secret
+        let mut game = Game::new(config, None);
+        game.secret = Secret::Evil(numbers);
+        Ok(game)
+    }
+
+    /// Tells if the game is played against the evil host
+    pub fn is_evil(&self) -> bool {
+        match self.secret {
+            Secret::Fixed(_) => false,
+            Secret::Evil(_) => true,
+        }
+    }
+}
+
+// Picks the answer to the guess that keeps the most numbers, and drops all other numbers. When two
+// answers keep the same number of them, the host never picks the winning one.
+pub(super) fn answer(numbers: &mut Vec<Vec<u8>>, guess: &[u8]) -> (u8, u8) {
+    let length = guess.len();
+
+    // Count the numbers that would give every answer. An answer of c cows and b bulls is counted
+    // at index c * (length + 1) + b, same as in solver::partition().
+    let side = length + 1;
+    let mut sizes = vec![0; side * side];
+    for number in numbers.iter() {
+        let (cows, bulls) = score(number, guess);
+        sizes[cows as usize * side + bulls as usize] += 1;
+    }
+
+    // The winning answer is the one with all bulls, and it only counts if nothing else is left.
+    // max_by_key() returns the last of equal elements, so we look through the answers backwards
+    // to pick the first one of the largest.
+    let best = (0..sizes.len())
+        .rev()
+        .max_by_key(|&index| (sizes[index], index % side != length))
+        .unwrap_or(0);
+    let answer = ((best / side) as u8, (best % side) as u8);
+
+    numbers.retain(|number| score(number, guess) == answer);
+    answer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::answer;
+    use game::{Alphabet, Game, GameConfig, Guess, score};
+    use solver::{Builtin, Solver};
+
+    // Plays the evil host until it's cornered. In the end the host must have a secret number that
+    // gives every answer it has given.
+    fn corner(config: GameConfig, strategy: Builtin) {
+        let mut game = Game::evil(config.clone()).unwrap();
+        let mut solver = Solver::new(&config, &strategy).unwrap();
+        loop {
+            // The host keeps exactly the numbers the solver thinks are possible
+            assert_eq!(game.reveal().is_some(), solver.candidates().len() == 1);
+            let guess = Guess::from_code(solver.next_guess().unwrap(), &config).unwrap();
+            let (cows, bulls, won) = game.play(&guess);
+            if won {
+                break;
+            }
+            assert!(game.tries < 20);
+            solver.record(guess.code(), cows, bulls);
+        }
+
+        let secret = game.reveal().unwrap();
+        for turn in &game.history {
+            assert_eq!(score(secret, &turn.guess), (turn.cows, turn.bulls));
+        }
+        assert_eq!(game.history.last().unwrap().bulls as usize, config.length());
+    }
+
+    #[test]
+    fn host_stays_consistent() {
+        let custom = Alphabet::Custom("abcde".chars().collect());
+        corner(GameConfig::new(3, custom.clone()).unwrap(), Builtin::First);
+        corner(GameConfig::new(3, custom.clone()).unwrap(), Builtin::Minimax);
+        corner(GameConfig::with_repeats(3, custom, true).unwrap(), Builtin::Entropy);
+        corner(GameConfig::default(), Builtin::First);
+    }
+
+    // When two answers keep as many numbers, the host doesn't give up
+    #[test]
+    fn no_win_on_a_tie() {
+        let mut numbers = vec![vec![0, 1, 2], vec![0, 1, 3]];
+        assert_eq!(answer(&mut numbers, &[0, 1, 2]), (0, 2));
+        assert_eq!(numbers, [vec![0, 1, 3]]);
+        assert_eq!(answer(&mut numbers, &[0, 1, 3]), (0, 3));
+    }
+
+    #[test]
+    fn too_many_numbers() {
+        assert!(Game::evil(GameConfig::new(10, Alphabet::Letters).unwrap()).is_err());
+        assert!(Game::evil(GameConfig::default()).unwrap().is_evil());
+    }
+}
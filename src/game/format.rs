@@ -0,0 +1,152 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module has the pieces every file of the game is made of, so saves, transcripts, stats,
+//! leaderboards and profiles all read and write them the same way.
+//!
+//! Every file starts with a header: a word that tells what the file is and the version of its
+//! format, like `cowbull-save 2`. The rules of a game are written as the code length, the
+//! alphabet and the word `repeats` if symbols can repeat:
+//!
+//! ```text
+//! 4 digits
+//! 6 custom:abcdef repeats
+//! ```
+//!
+//! A preset alphabet is written by its name and a custom one as `custom:` followed by its
+//! symbols. The prefix keeps custom symbols that spell the name of a preset from turning into the
+//! preset, and there's no space after it, so the alphabet is always a single word.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use super::{Alphabet, GameConfig};
+
+// The prefix of a custom alphabet
+const CUSTOM: &str = "custom:";
+
+/// What can be wrong with the first line of a file
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum HeaderError {
+    /// The line doesn't start with the word we expect, so it's some other file
+    Foreign,
+    /// The version is not a number
+    BadVersion,
+    /// The file uses a version of the format we can't read, most likely a newer one
+    Version(u32),
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeaderError::Foreign => write!(f, "unknown kind of file"),
+            HeaderError::BadVersion => write!(f, "bad version"),
+            HeaderError::Version(version) => write!(f, "format version {} is not supported", version),
+        }
+    }
+}
+
+/// Writes the header of a file
+pub fn write_header<W: Write>(out: &mut W, magic: &str, version: u32) -> io::Result<()> {
+    writeln!(out, "{} {}", magic, version)
+}
+
+/// Checks the first line of a file and returns the version of its format. The versions are the
+/// ones we can still read, oldest to latest. The version is compared as a number, so `01` is the
+/// same as `1`, and a newer version is told apart from garbage.
+pub fn check_header(line: &str, magic: &str, versions: RangeInclusive<u32>) -> Result<u32, HeaderError> {
+    match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+        [word, found] if *word == magic => match found.parse::<u32>() {
+            Ok(found) if versions.contains(&found) => Ok(found),
+            Ok(found) => Err(HeaderError::Version(found)),
+            Err(_) => Err(HeaderError::BadVersion),
+        },
+        _ => Err(HeaderError::Foreign),
+    }
+}
+
+/// The word for an alphabet: the name of a preset or the symbols after `custom:`
+pub fn alphabet_word(alphabet: &Alphabet) -> String {
+    match alphabet.name() {
+        Some(name) => name.to_string(),
+        None => format!("{}{}", CUSTOM, alphabet.symbols().iter().collect::<String>()),
+    }
+}
+
+/// Turns the word back into an alphabet
+pub fn parse_alphabet(word: &str) -> Alphabet {
+    match word.strip_prefix(CUSTOM) {
+        Some(symbols) => Alphabet::Custom(symbols.chars().collect()),
+        None => Alphabet::from_name(word),
+    }
+}
+
+/// The rules as words of a line: the code length, the alphabet and `repeats` if it's allowed
+pub fn rules_words(config: &GameConfig) -> String {
+    let words = format!("{} {}", config.length(), alphabet_word(config.alphabet()));
+    if config.repeats() {
+        words + " repeats"
+    } else {
+        words
+    }
+}
+
+/// Reads the rules back from the words of a line. The flags are the words after the alphabet, and
+/// the rules only care if `repeats` is among them, so a file can put its own flags there too.
+pub fn parse_rules(length: &str, alphabet: &str, flags: &[&str]) -> Result<GameConfig, String> {
+    let length = parse_number(length)?;
+    GameConfig::with_repeats(length, parse_alphabet(alphabet), flags.contains(&"repeats")).map_err(|error| error.to_string())
+}
+
+/// Parses a number from a file. The type of the number is generic, so the same function reads u8
+/// counters and u64 times. The error is a message for the player.
+pub fn parse_number<T: FromStr>(value: &str) -> Result<T, String> {
+    value.parse::<T>().map_err(|_| format!("\"{}\" is not a number", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeaderError, check_header, parse_rules, rules_words};
+    use game::{Alphabet, GameConfig};
+
+    #[test]
+    fn rules_round_trip() {
+        let configs = [
+            GameConfig::default(),
+            GameConfig::with_repeats(6, Alphabet::Hex, true).unwrap(),
+            GameConfig::with_repeats(4, Alphabet::Custom("xy!".chars().collect()), true).unwrap(),
+            // Custom symbols that spell a preset must stay custom
+            GameConfig::new(3, Alphabet::Custom("hex".chars().collect())).unwrap(),
+        ];
+        for config in &configs {
+            let words = rules_words(config);
+            let words = words.split_whitespace().collect::<Vec<&str>>();
+            assert_eq!(parse_rules(words[0], words[1], &words[2..]).as_ref(), Ok(config));
+        }
+        assert_eq!(rules_words(&configs[1]), "6 hex repeats");
+        assert_eq!(rules_words(&configs[3]), "3 custom:hex");
+    }
+
+    #[test]
+    fn bad_rules_are_rejected() {
+        assert!(parse_rules("four", "digits", &[]).is_err());
+        assert!(parse_rules("11", "digits", &[]).is_err());
+        assert!(parse_rules("4", "custom:abc", &[]).is_err());
+        assert!(parse_rules("4", "custom:abc", &["repeats"]).is_ok());
+    }
+
+    #[test]
+    fn header() {
+        assert_eq!(check_header("cowbull-save 1", "cowbull-save", 1..=2), Ok(1));
+        assert_eq!(check_header("cowbull-save 02", "cowbull-save", 1..=2), Ok(2));
+        assert_eq!(check_header("cowbull-save 3", "cowbull-save", 1..=2), Err(HeaderError::Version(3)));
+        assert_eq!(check_header("cowbull-save 1", "cowbull-save", 2..=2), Err(HeaderError::Version(1)));
+        assert_eq!(check_header("cowbull-save one", "cowbull-save", 1..=2), Err(HeaderError::BadVersion));
+        assert_eq!(check_header("cowbull-stats 1", "cowbull-save", 1..=2), Err(HeaderError::Foreign));
+        assert_eq!(check_header("", "cowbull-save", 1..=2), Err(HeaderError::Foreign));
+    }
+}
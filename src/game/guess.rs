@@ -0,0 +1,175 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module turns what the player has typed into a guess the game can score. Parsing can fail
+//! in a few different ways and each of them gets its own error, so the caller can tell the player
+//! exactly what is wrong instead of crashing on a bad character.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::config::{Alphabet, GameConfig};
+
+/// A guess that is known to be valid for some rules. Like the secret number, it's stored as
+/// a vector of symbol indices. The field is private, so a guess can only be made by parsing.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Guess {
+    code: Vec<u8>,
+}
+
+/// Everything that can be wrong with a guess
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum GuessError {
+    /// The guess has a wrong number of symbols: how many are needed and how many we've got
+    WrongLength(usize, usize),
+    /// A character that is not a decimal digit in a game of digits, and its position counting
+    /// from one, the way players count
+    NotADigit(usize, char),
+    /// A character that is not in the alphabet of the game, and its position
+    OutsideAlphabet(usize, char),
+    /// A digit that is used more than once in a game of digits
+    RepeatedDigit(char),
+    /// A symbol that is used more than once in a game of any other alphabet
+    RepeatedSymbol(char),
+}
+
+impl fmt::Display for GuessError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GuessError::WrongLength(expected, found) =>
+                write!(f, "Number of {} symbols is needed, got {}", expected, found),
+            GuessError::NotADigit(position, symbol) =>
+                write!(f, "'{}' at position {} is not a digit", symbol, position),
+            GuessError::OutsideAlphabet(position, symbol) =>
+                write!(f, "'{}' at position {} is not one of the game symbols", symbol, position),
+            GuessError::RepeatedDigit(symbol) =>
+                write!(f, "Digits must be unique, '{}' is repeated", symbol),
+            GuessError::RepeatedSymbol(symbol) =>
+                write!(f, "Symbols must be unique, '{}' is repeated", symbol),
+        }
+    }
+}
+
+impl GuessError {
+
+    /// The error for a repeated symbol. Like with a wrong character, in the classic game we can
+    /// simply talk about digits.
+    pub fn repeated(symbol: char, config: &GameConfig) -> GuessError {
+        if *config.alphabet() == Alphabet::Digits {
+            GuessError::RepeatedDigit(symbol)
+        } else {
+            GuessError::RepeatedSymbol(symbol)
+        }
+    }
+}
+
+impl Guess {
+
+    /// Parses a string according to the rules of a game. We check every character first, then the
+    /// length, and then look for repeated symbols.
+    pub fn parse(value: &str, config: &GameConfig) -> Result<Guess, GuessError> {
+        let mut code = Vec::with_capacity(config.length());
+
+        for (i, symbol) in value.chars().enumerate() {
+            match config.index_of(symbol) {
+                Some(index) => code.push(index),
+                // In the classic game we can simply say it's not a digit
+                None if *config.alphabet() == Alphabet::Digits => return Err(GuessError::NotADigit(i + 1, symbol)),
+                None => return Err(GuessError::OutsideAlphabet(i + 1, symbol)),
+            }
+        }
+
+        if code.len() != config.length() {
+            return Err(GuessError::WrongLength(config.length(), code.len()));
+        }
+
+        // Compare every symbol with the ones that follow it to find duplicates, unless the rules
+        // allow them
+        if !config.repeats() {
+            for (i, index) in code.iter().enumerate() {
+                if code[(i + 1)..].contains(index) {
+                    return Err(GuessError::repeated(config.symbol(*index), config));
+                }
+            }
+        }
+
+        Ok(Guess { code })
+    }
+
+    /// Wraps a code of symbol indices, for example the one chosen by the solver, without going
+    /// through the text. Returns None if the code doesn't fit the rules: a wrong length, an index
+    /// outside of the alphabet or a repeated symbol when repeats are not allowed.
+    pub fn from_code(code: Vec<u8>, config: &GameConfig) -> Option<Guess> {
+        if config.is_valid_code(&code) {
+            Some(Guess { code })
+        } else {
+            None
+        }
+    }
+
+    /// The symbol indices of this guess
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+}
+
+// FromStr is the trait behind the parse() method of strings. It can't know the rules of the game,
+// so "1234".parse::<Guess>() uses the classic rules of four unique digits.
+impl FromStr for Guess {
+    type Err = GuessError;
+
+    fn from_str(value: &str) -> Result<Guess, GuessError> {
+        Guess::parse(value, &GameConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Guess, GuessError};
+    use super::GuessError::*;
+    use game::{Alphabet, GameConfig};
+
+    fn hex() -> GameConfig {
+        GameConfig::new(4, Alphabet::Hex).unwrap()
+    }
+
+    #[test]
+    fn good_guesses() {
+        assert_eq!("0123".parse::<Guess>().unwrap().code(), &[0, 1, 2, 3]);
+        assert_eq!(Guess::parse("0a9f", &hex()).unwrap().code(), &[0, 10, 9, 15]);
+        let repeats = GameConfig::with_repeats(4, Alphabet::Digits, true).unwrap();
+        assert_eq!(Guess::parse("1111", &repeats).unwrap().code(), &[1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn bad_guesses() {
+        assert_eq!("012".parse::<Guess>(), Err(WrongLength(4, 3)));
+        assert_eq!("01234".parse::<Guess>(), Err(WrongLength(4, 5)));
+        assert_eq!("01a3".parse::<Guess>(), Err(NotADigit(3, 'a')));
+        assert_eq!(Guess::parse("01g3", &hex()), Err(OutsideAlphabet(3, 'g')));
+        assert_eq!("0121".parse::<Guess>(), Err(RepeatedDigit('1')));
+        assert_eq!(Guess::parse("0aa1", &hex()), Err(RepeatedSymbol('a')));
+        // A bad character is reported before a wrong length
+        assert_eq!("0x".parse::<Guess>(), Err(NotADigit(2, 'x')));
+    }
+
+    // Only the classic game talks about digits
+    #[test]
+    fn repeated_wording() {
+        assert_eq!(GuessError::repeated('1', &GameConfig::default()).to_string(),
+                   "Digits must be unique, '1' is repeated");
+        assert_eq!(GuessError::repeated('1', &hex()).to_string(),
+                   "Symbols must be unique, '1' is repeated");
+    }
+
+    #[test]
+    fn codes() {
+        let config = GameConfig::default();
+        assert!(Guess::from_code(vec![9, 8, 7, 6], &config).is_some());
+        assert!(Guess::from_code(vec![9, 8, 7], &config).is_none());
+        assert!(Guess::from_code(vec![9, 8, 7, 10], &config).is_none());
+        assert!(Guess::from_code(vec![9, 8, 7, 9], &config).is_none());
+    }
+}
@@ -0,0 +1,173 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! Hard mode is a challenge rule: every guess must agree with all answers heard so far. In other
+//! words, every guess must be a number that could still be the secret. A guess that breaks the rule
+//! is rejected, and we tell the player which answer it contradicts and why.
+
+use std::fmt;
+
+use super::{Game, GameConfig, Guess, Turn, score};
+
+/// Ways a guess can contradict an earlier answer. Every variant starts with the number of the
+/// earlier guess, counting from one, and the answer it got.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Contradiction {
+    /// The guess keeps more symbols of the earlier guess in place than it had bulls. The symbols
+    /// are listed with their positions, counting from one.
+    TooManyBulls(usize, u8, Vec<(usize, char)>),
+    /// The guess keeps fewer symbols of the earlier guess in place than it had bulls
+    TooFewBulls(usize, u8, usize),
+    /// The guess uses more symbols of the earlier guess than it had cows and bulls together
+    TooManyCommon(usize, u8, Vec<char>),
+    /// The guess uses fewer symbols of the earlier guess than it had cows and bulls together
+    TooFewCommon(usize, u8, usize),
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Contradiction::TooManyBulls(turn, bulls, ref kept) => {
+                let kept = kept.iter()
+                    .map(|&(position, symbol)| format!("{} in position {}", symbol, position))
+                    .collect::<Vec<String>>();
+                write!(f, "Guess {} scored {} bulls, but your guess keeps {}", turn, bulls, list(&kept))
+            },
+            Contradiction::TooFewBulls(turn, bulls, 0) =>
+                write!(f, "Guess {} scored {} bulls, but your guess keeps none of its symbols in place", turn, bulls),
+            Contradiction::TooFewBulls(turn, bulls, kept) =>
+                write!(f, "Guess {} scored {} bulls, but your guess keeps only {} of its symbols in place", turn, bulls, kept),
+            Contradiction::TooManyCommon(turn, matches, ref used) => {
+                let used = used.iter().map(|symbol| symbol.to_string()).collect::<Vec<String>>();
+                write!(f, "Guess {} scored {} cows and bulls, but your guess uses {}", turn, matches, list(&used))
+            },
+            Contradiction::TooFewCommon(turn, matches, 0) =>
+                write!(f, "Guess {} scored {} cows and bulls, but your guess uses none of its symbols", turn, matches),
+            Contradiction::TooFewCommon(turn, matches, used) =>
+                write!(f, "Guess {} scored {} cows and bulls, but your guess uses only {} of its symbols", turn, matches, used),
+        }
+    }
+}
+
+// Joins a few things the way people list them: "a", "a and b", "a, b and c"
+fn list(items: &[String]) -> String {
+    match items.split_last() {
+        Some((last, others)) if !others.is_empty() => format!("{} and {}", others.join(", "), last),
+        _ => items.join(""),
+    }
+}
+
+impl Game {
+
+    /// Checks the guess against the rule of hard mode. Outside of hard mode every guess is fine.
+    /// Returns the first contradiction with an earlier answer, if there's any.
+    pub fn check_hard(&self, variant: &Guess) -> Result<(), Contradiction> {
+        if !self.hard {
+            return Ok(());
+        }
+
+        for (i, turn) in self.history.iter().enumerate() {
+            check_turn(&self.config, i + 1, turn, variant.code())?;
+        }
+        Ok(())
+    }
+}
+
+// Checks the guess against one earlier answer. Scoring works both ways: if the guess was the
+// secret, the earlier guess would get the same answer as it did from the real secret.
+fn check_turn(config: &GameConfig, number: usize, turn: &Turn, code: &[u8]) -> Result<(), Contradiction> {
+    let (cows, bulls) = score(code, &turn.guess);
+    let matches = turn.cows + turn.bulls;
+
+    // Bulls are easy to explain, so we look at them first
+    if bulls > turn.bulls {
+        let kept = code.iter()
+            .zip(turn.guess.iter())
+            .enumerate()
+            .filter(|&(_, (symbol, other))| symbol == other)
+            .map(|(position, (&symbol, _))| (position + 1, config.symbol(symbol)))
+            .collect();
+        return Err(Contradiction::TooManyBulls(number, turn.bulls, kept));
+    }
+    if bulls < turn.bulls {
+        return Err(Contradiction::TooFewBulls(number, turn.bulls, bulls as usize));
+    }
+
+    // The bulls are right, so the cows must be wrong. We list the common symbols, each of them
+    // once, in the order of the guess.
+    if cows + bulls > matches {
+        let mut used = Vec::new();
+        for &symbol in code {
+            let symbol = config.symbol(symbol);
+            if turn.guess.iter().any(|&other| config.symbol(other) == symbol) && !used.contains(&symbol) {
+                used.push(symbol);
+            }
+        }
+        return Err(Contradiction::TooManyCommon(number, matches, used));
+    }
+    if cows + bulls < matches {
+        return Err(Contradiction::TooFewCommon(number, matches, (cows + bulls) as usize));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Contradiction::*;
+    use game::{Game, GameConfig, Guess};
+
+    // A game of 1234 in hard mode with the guesses already played
+    fn game(guesses: &[&str]) -> Game {
+        let mut game = Game::with_secret(GameConfig::default(), &"1234".parse().unwrap());
+        game.hard = true;
+        for guess in guesses {
+            game.play(&guess.parse().unwrap());
+        }
+        game
+    }
+
+    fn check(game: &Game, guess: &str) -> Result<(), super::Contradiction> {
+        game.check_hard(&guess.parse::<Guess>().unwrap())
+    }
+
+    #[test]
+    fn bulls() {
+        // 1256 scores two bulls
+        let game = game(&["1256"]);
+        assert_eq!(check(&game, "1278"), Ok(()));
+        assert_eq!(check(&game, "1253"), Err(TooManyBulls(1, 2, vec![(1, '1'), (2, '2'), (3, '5')])));
+        assert_eq!(check(&game, "1789"), Err(TooFewBulls(1, 2, 1)));
+        assert_eq!(check(&game, "7890").unwrap_err().to_string(),
+                   "Guess 1 scored 2 bulls, but your guess keeps none of its symbols in place");
+    }
+
+    #[test]
+    fn common_symbols() {
+        let first = game(&["1256"]);
+        assert_eq!(check(&first, "1265"), Err(TooManyCommon(1, 2, vec!['1', '2', '6', '5'])));
+
+        // 1325 scores a bull and two cows
+        let second = game(&["1325"]);
+        assert_eq!(check(&second, "1789"), Err(TooFewCommon(1, 3, 1)));
+        assert_eq!(check(&second, "1789").unwrap_err().to_string(),
+                   "Guess 1 scored 3 cows and bulls, but your guess uses only 1 of its symbols");
+    }
+
+    // 5290 contradicts both answers, and the first one is the one we report
+    #[test]
+    fn earliest_turn() {
+        let game = game(&["5678", "1256"]);
+        assert_eq!(check(&game, "5290"), Err(TooManyBulls(1, 0, vec![(1, '5')])));
+        assert_eq!(check(&game, "1290"), Ok(()));
+    }
+
+    #[test]
+    fn only_in_hard_mode() {
+        let mut game = game(&["1256"]);
+        game.hard = false;
+        assert_eq!(check(&game, "7890"), Ok(()));
+    }
+}
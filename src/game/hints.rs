@@ -0,0 +1,431 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module computes the hint table from the whole history of a game. Instead of guessing
+//! which conclusions are safe, we look at every secret number that still agrees with all answers.
+//! A symbol is surely at a position if every such number has it there, and surely not if none
+//! has. This is what a player with a perfect memory and a lot of patience could figure out.
+//!
+//! We can't just check every possible number, because 26 letters on 10 positions make way too
+//! many of them. So we build numbers position by position and drop a half-built number as soon as
+//! it can't agree with some answer anymore. This is called backtracking search.
+
+use super::{GameConfig, Hint, Turn};
+
+// We count the numbers that agree with the answers, but stop when there are more than this.
+// In that case we still find out which cells are sure, but we don't count the chances.
+const COUNT_LIMIT: usize = 50_000;
+
+// Some searches can take forever, so every search gets a budget of symbols it can try to put in
+// place. A search that runs out of budget doesn't tell us anything, and the cell stays Unknown.
+// This way the table can miss a conclusion, but it never shows a wrong one.
+const COUNT_BUDGET: usize = 500_000;
+const CELL_BUDGET: usize = 5_000;
+
+/// Computes the hint table for the history of a game. The table has one line per symbol and one
+/// column per position, same as Game::hint_table.
+pub fn hint_table(config: &GameConfig, history: &[Turn]) -> Vec<Vec<Hint>> {
+    let symbols = config.symbols().len();
+    let length = config.length();
+
+    // Before the first answer we know nothing at all
+    if history.is_empty() {
+        return vec![vec![Hint::Unknown; length]; symbols];
+    }
+
+    // Try to count how many numbers have every symbol at every position
+    let mut counts = vec![vec![0usize; length]; symbols];
+    let mut total = 0;
+    let finished = Search::new(config, history, None, COUNT_BUDGET).run(&mut |code| {
+        for (position, symbol) in code.iter().enumerate() {
+            counts[*symbol as usize][position] += 1;
+        }
+        total += 1;
+        total <= COUNT_LIMIT
+    });
+
+    if finished {
+        // We have the exact counts, so every cell is easy to judge
+        return counts.iter()
+            .map(|line| line.iter().map(|&count| match count {
+                0 => Hint::NotHere,
+                count if count == total => Hint::Here,
+                count => Hint::Maybe(count as f32 / total as f32),
+            }).collect())
+            .collect();
+    }
+
+    // There are too many numbers to count. But every number we've found is a witness: each of
+    // its symbols can be at its position, and no other symbol has to be there. We keep two tables
+    // of what our witnesses have shown so far.
+    let mut seen = counts.iter()
+        .map(|line| line.iter().map(|&count| count > 0).collect::<Vec<bool>>())
+        .collect::<Vec<Vec<bool>>>();
+    let mut missed = counts.iter()
+        .map(|line| line.iter().map(|&count| count < total).collect::<Vec<bool>>())
+        .collect::<Vec<Vec<bool>>>();
+
+    let mut table = vec![vec![Hint::Unknown; length]; symbols];
+    for symbol in 0..symbols {
+        for position in 0..length {
+            let cell = (position, symbol as u8);
+
+            // If no witness had the symbol here, we look for a number that has it. If there's
+            // none, the symbol is surely not here.
+            if !seen[symbol][position] {
+                match find(config, history, Some((cell, true))) {
+                    Found::Code(code) => witness(&code, &mut seen, &mut missed),
+                    Found::Nothing => table[symbol][position] = Hint::NotHere,
+                    Found::GaveUp => (),
+                }
+            }
+
+            // If every witness had the symbol here, we look for a number that doesn't. If there's
+            // none, the symbol is surely here.
+            if seen[symbol][position] && !missed[symbol][position] {
+                match find(config, history, Some((cell, false))) {
+                    Found::Code(code) => witness(&code, &mut seen, &mut missed),
+                    Found::Nothing => table[symbol][position] = Hint::Here,
+                    Found::GaveUp => (),
+                }
+            }
+        }
+    }
+    table
+}
+
+/// Returns every number that agrees with the history, or None if there are more than the limit
+/// or it takes too long to find them all
+pub fn consistent_codes(config: &GameConfig, history: &[Turn], limit: usize) -> Option<Vec<Vec<u8>>> {
+    let mut codes = Vec::new();
+    let finished = Search::new(config, history, None, COUNT_BUDGET).run(&mut |code| {
+        codes.push(code.to_vec());
+        codes.len() <= limit
+    });
+
+    if finished {
+        Some(codes)
+    } else {
+        None
+    }
+}
+
+// What a search for a single number has found
+enum Found {
+    // A number that agrees with everything
+    Code(Vec<u8>),
+    // There's surely no such number
+    Nothing,
+    // The search has run out of budget
+    GaveUp,
+}
+
+// Looks for a number that agrees with the history and the cell rule
+fn find(config: &GameConfig, history: &[Turn], rule: Option<((usize, u8), bool)>) -> Found {
+    let mut search = Search::new(config, history, rule, CELL_BUDGET);
+
+    // The visitor saves the number and stops the search right away
+    let mut found = None;
+    let finished = search.run(&mut |code| {
+        found = Some(code.to_vec());
+        false
+    });
+
+    match found {
+        Some(code) => Found::Code(code),
+        None if finished => Found::Nothing,
+        None => Found::GaveUp,
+    }
+}
+
+// Marks what a witness number shows: its symbols can be where they are, and every other symbol
+// doesn't have to be at those positions
+fn witness(code: &[u8], seen: &mut [Vec<bool>], missed: &mut [Vec<bool>]) {
+    for (position, &symbol) in code.iter().enumerate() {
+        seen[symbol as usize][position] = true;
+        for (other, line) in missed.iter_mut().enumerate() {
+            if other != symbol as usize {
+                line[position] = true;
+            }
+        }
+    }
+}
+
+// The state of a backtracking search
+struct Search<'a> {
+    config: &'a GameConfig,
+    history: &'a [Turn],
+    // An extra rule for one cell: the symbol must (true) or must not (false) be at the position
+    rule: Option<((usize, u8), bool)>,
+    // The order in which we fill the positions. The cell of the rule goes first, so a number
+    // that breaks the rule is dropped right away instead of at the very end.
+    order: Vec<usize>,
+    // How many positions of the order are filled already
+    depth: usize,
+    // The half-built number. Positions that are not filled yet hold garbage.
+    code: Vec<u8>,
+    // How many times every symbol is used in it
+    used: Vec<u8>,
+    // For every turn, how many bulls and how many common symbols (cows plus bulls) the half-built
+    // number has with the guess of that turn
+    bulls: Vec<u8>,
+    common: Vec<u8>,
+    // For every turn and every symbol, how many times the guess has that symbol. A symbol of our
+    // number is common with the guess only while it's used no more times than this.
+    wanted: Vec<Vec<u8>>,
+    // How many more half-built numbers we can look at
+    budget: usize,
+}
+
+impl<'a> Search<'a> {
+
+    fn new(config: &'a GameConfig, history: &'a [Turn], rule: Option<((usize, u8), bool)>, budget: usize) -> Search<'a> {
+        let symbols = config.symbols().len();
+
+        // Count the symbols of every guess once, so we don't have to search the guesses over and
+        // over again
+        let wanted = history.iter()
+            .map(|turn| {
+                let mut wanted = vec![0; symbols];
+                for symbol in &turn.guess {
+                    wanted[*symbol as usize] += 1;
+                }
+                wanted
+            })
+            .collect();
+
+        // Natural order of positions, except the cell of the rule is moved to the front
+        let mut order = (0..config.length()).collect::<Vec<usize>>();
+        if let Some(((position, _), _)) = rule {
+            order.retain(|&other| other != position);
+            order.insert(0, position);
+        }
+
+        Search {
+            config,
+            history,
+            rule,
+            order,
+            depth: 0,
+            code: vec![0; config.length()],
+            used: vec![0; symbols],
+            bulls: vec![0; history.len()],
+            common: vec![0; history.len()],
+            wanted,
+            budget,
+        }
+    }
+
+    // Calls the visitor for every number that agrees with the history. The visitor returns false
+    // to stop the search. Returns true if the search has finished without being stopped by the
+    // visitor or by running out of budget.
+    fn run<F: FnMut(&[u8]) -> bool>(&mut self, visit: &mut F) -> bool {
+        if self.depth == self.order.len() {
+            return visit(&self.code);
+        }
+        let position = self.order[self.depth];
+
+        for symbol in (0..self.config.symbols().len()).map(|symbol| symbol as u8) {
+            let repeated = self.used[symbol as usize] > 0 && !self.config.repeats();
+            if repeated || !self.allowed(position, symbol) {
+                continue;
+            }
+
+            // Every symbol we try costs us one point of the budget
+            if self.budget == 0 {
+                return false;
+            }
+            self.budget -= 1;
+
+            // Put the symbol in place and update the counters of every turn
+            self.code[position] = symbol;
+            self.used[symbol as usize] += 1;
+            self.depth += 1;
+            self.count(position, symbol, true);
+
+            let keep_going = !self.possible() || self.run(visit);
+
+            // Take the symbol back, so the next one can be tried
+            self.count(position, symbol, false);
+            self.depth -= 1;
+            self.used[symbol as usize] -= 1;
+
+            if !keep_going {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    // Adds the symbol that was just put at the position to the counters of every turn, or removes
+    // it from them when it's taken back. The symbol is already counted in self.used either way.
+    fn count(&mut self, position: usize, symbol: u8, add: bool) {
+        let used = self.used[symbol as usize];
+        for (i, turn) in self.history.iter().enumerate() {
+            let bull = (turn.guess[position] == symbol) as u8;
+            let common = (used <= self.wanted[i][symbol as usize]) as u8;
+            if add {
+                self.bulls[i] += bull;
+                self.common[i] += common;
+            } else {
+                self.bulls[i] -= bull;
+                self.common[i] -= common;
+            }
+        }
+    }
+
+    // Checks the extra cell rule for a symbol at a position
+    fn allowed(&self, position: usize, symbol: u8) -> bool {
+        match self.rule {
+            Some(((cell_position, cell_symbol), here)) if cell_position == position =>
+                (symbol == cell_symbol) == here,
+            _ => true,
+        }
+    }
+
+    // Checks if the half-built number can still agree with every answer. The more numbers we
+    // drop early here, the faster the search goes, so we look at a few things for every turn.
+    fn possible(&self) -> bool {
+        let left = self.order.len() - self.depth;
+        let empty = &self.order[self.depth..];
+
+        // Symbols that are not used yet, we'll need their count below
+        let unused = self.used.iter().filter(|&&used| used == 0).count();
+
+        self.history.iter().enumerate().all(|(i, turn)| {
+            let matches = (turn.cows + turn.bulls) as usize;
+            let (bulls, common) = (self.bulls[i] as usize, self.common[i] as usize);
+
+            // We can't have more bulls or common symbols than the answer says
+            if bulls > turn.bulls as usize || common > matches {
+                return false;
+            }
+            let missing_bulls = turn.bulls as usize - bulls;
+            let missing_common = matches - common;
+
+            // With repeated symbols we can't say much more. Every position adds one bull and one
+            // common symbol at most, but a symbol that was common can become a bull later without
+            // adding anything, so bulls and common symbols don't go together.
+            if self.config.repeats() {
+                return missing_bulls <= left && missing_common <= left;
+            }
+
+            // A bull can only come from a position that is still empty and whose symbol in the
+            // guess is not used yet
+            let free_bulls = empty.iter()
+                .filter(|&&position| self.used[turn.guess[position] as usize] == 0)
+                .count();
+
+            // A common symbol can only be a symbol of the guess that is not used yet, and all
+            // other empty positions must be filled with symbols that are not in the guess
+            let free_common = turn.guess.iter()
+                .filter(|&&symbol| self.used[symbol as usize] == 0)
+                .count();
+            let free_other = unused - free_common;
+
+            // Every missing bull is also a missing common symbol
+            missing_bulls <= missing_common
+                && missing_bulls <= free_bulls
+                && missing_common <= free_common.min(left)
+                && left - missing_common <= free_other
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hint_table;
+    use game::{Alphabet, Game, GameConfig, Guess, Hint, Turn, score};
+    use solver::all_codes;
+
+    // Plays the guesses against the secret number made from the seed and returns the history
+    fn play(config: &GameConfig, seed: u64, guesses: &[&str]) -> Vec<Turn> {
+        let mut game = Game::new(config.clone(), Some(seed));
+        for guess in guesses {
+            game.try(&Guess::parse(guess, config).unwrap());
+        }
+        game.history
+    }
+
+    // Counts the slow way how many of the numbers that agree with the history have every symbol
+    // at every position, and how many numbers agree at all
+    fn brute_force(config: &GameConfig, history: &[Turn]) -> (Vec<Vec<usize>>, usize) {
+        let mut counts = vec![vec![0; config.length()]; config.symbols().len()];
+        let mut total = 0;
+        for code in all_codes(config).unwrap() {
+            if history.iter().all(|turn| score(&code, &turn.guess) == (turn.cows, turn.bulls)) {
+                for (position, &symbol) in code.iter().enumerate() {
+                    counts[symbol as usize][position] += 1;
+                }
+                total += 1;
+            }
+        }
+        (counts, total)
+    }
+
+    // When the numbers can be counted, every cell must be exactly what the brute force says
+    fn check_exact(config: GameConfig, seed: u64, guesses: &[&str]) {
+        let history = play(&config, seed, guesses);
+        let table = hint_table(&config, &history);
+        let (counts, total) = brute_force(&config, &history);
+
+        for (symbol, line) in counts.iter().enumerate() {
+            for (position, &count) in line.iter().enumerate() {
+                match table[symbol][position] {
+                    Hint::NotHere => assert_eq!(count, 0),
+                    Hint::Here => assert_eq!(count, total),
+                    Hint::Maybe(chance) => {
+                        assert!(count > 0 && count < total);
+                        assert!((chance - count as f32 / total as f32).abs() < 1e-4);
+                    },
+                    Hint::Unknown => panic!("cell {} {} is unknown", symbol, position),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_is_known_before_the_first_guess() {
+        let config = GameConfig::default();
+        let table = hint_table(&config, &[]);
+        assert!(table.iter().all(|line| line.iter().all(|&hint| hint == Hint::Unknown)));
+    }
+
+    #[test]
+    fn classic_hints_match_brute_force() {
+        check_exact(GameConfig::default(), 42, &["0123"]);
+        check_exact(GameConfig::default(), 42, &["0123", "1456", "2578"]);
+        check_exact(GameConfig::default(), 7, &["9876", "0123", "4567"]);
+    }
+
+    #[test]
+    fn hints_with_repeats_match_brute_force() {
+        let config = GameConfig::with_repeats(4, Alphabet::Custom("abcdef".chars().collect()), true).unwrap();
+        check_exact(config.clone(), 3, &["aabb"]);
+        check_exact(config, 3, &["aabb", "abcd", "ffea"]);
+    }
+
+    // With too many numbers to count, some cells can stay unknown, but a sure cell must be right
+    #[test]
+    fn sure_hints_are_right_without_counting() {
+        let config = GameConfig::new(5, Alphabet::Hex).unwrap();
+        let history = play(&config, 11, &["01234"]);
+        let table = hint_table(&config, &history);
+        let (counts, total) = brute_force(&config, &history);
+        assert!(total > super::COUNT_LIMIT);
+
+        for (symbol, line) in counts.iter().enumerate() {
+            for (position, &count) in line.iter().enumerate() {
+                match table[symbol][position] {
+                    Hint::NotHere => assert_eq!(count, 0),
+                    Hint::Here => assert_eq!(count, total),
+                    Hint::Maybe(_) => assert!(count > 0 && count < total),
+                    Hint::Unknown => (),
+                }
+            }
+        }
+    }
+}
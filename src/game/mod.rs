@@ -3,310 +3,427 @@
     See LICENSE file for legal stuff
 */
 
+//! The game engine: a secret number, the guesses made against it and the hints on where every
+//! symbol could be. The rules, parsing, hints and saving live in submodules and are re-exported
+//! here, so everything can be used as cowbull::game::Something.
+
 // We'll use external implementation of random number generator. Note that we use self::* scope
 // to actually use something from this library because we are inside the game module and use
 // expects absolute path.
 extern crate rand;
-use self::rand::Rng;
-
-// Here we define a enum type that we'll use to print a table of hints for digits and their
-// potential positions in the secret number. Here we use a derive pragma that tells the Rust to
-// throw a couple of traits to our new type. Eq and PartialEq are used for comparison between
-// different variables of this type. Copy and Clone traits are needed to actually create a copy of
-// a value. Note that we use "pub" operator before enum definition, so the main program will be
-// able to see this type after it will import it into it's scope.
-#[derive(Eq, PartialEq, Copy, Clone)]
+use self::rand::{Rng, SeedableRng, XorShiftRng};
+
+// We'll remember when the game has started and when every guess was made
+use std::time::SystemTime;
+
+// The secret number can be shared by the games of many players, even in different threads
+use std::sync::Arc;
+
+// The rules of a game live in their own file. We declare the submodule and re-export everything
+// from it, so users of the game module see GameConfig as if it was defined right here.
+pub mod config;
+pub use self::config::*;
+
+// Same goes for parsing guesses typed by the player
+pub mod guess;
+pub use self::guess::*;
+
+// The hint table is computed by its own module, we'll call it as hints::hint_table()
+pub mod hints;
+
+// The header and the rules are written the same way in every file of the game
+pub mod format;
+
+// Saving a game to a file and loading it back adds a couple of methods to the Game
+pub mod save;
+pub use self::save::*;
+
+// The daily puzzle gets its seed from the date
+pub mod daily;
+
+// Hard mode checks every guess against the earlier answers
+pub mod hard;
+pub use self::hard::*;
+
+// The evil host doesn't make up its secret number until it has to
+pub mod evil;
+
+/// Here we define a enum type that we'll use to print a table of hints for digits and their
+/// potential positions in the secret number. Here we use a derive pragma that tells the Rust to
+/// throw a couple of traits to our new type. PartialEq is used for comparison between different
+/// variables of this type. We can't have Eq here, because floating point numbers are not always
+/// equal to themselves (look up NaN). Copy and Clone traits are needed to actually create a copy
+/// of a value. Note that we use "pub" operator before enum definition, so the main program will be
+/// able to see this type after it will import it into it's scope.
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum Hint {
-    // We don't really know anything about a digit in hinted position
+    /// We don't really know anything about a digit in hinted position
     Unknown,
-    // The digit maybe is here
-    Maybe,
-    // The digit is definitely present at this position
+    /// The digit maybe is here. An enum variant can carry a value, and this one carries the share
+    /// of still possible secret numbers that have the digit here, from 0 to 1.
+    Maybe(f32),
+    /// The digit is definitely present at this position
     Here,
-    // The digit is definitely not here
+    /// The digit is definitely not here
     NotHere,
 }
 
-// Here is our game structure that contains data that we'll need to play. This definition is public
-// so it can be called from the outside of this module.
+// The secret number of a game. Usually it's made up once at the start of the game, but the evil
+// host keeps every number that agrees with its answers and picks one only when it's forced to.
+// The enum is private, so the rest of the program can't tell one kind of game from the other.
+enum Secret {
+    // A number that never changes. It never changes, so many games can share it: Arc counts the
+    // games that point at the number and frees it when the last of them is gone.
+    Fixed(Arc<Vec<u8>>),
+    // Every number that agrees with the answers given so far
+    Evil(Vec<Vec<u8>>),
+}
+
+/// Every guess that was scored is remembered as a turn. The guess is stored as a vector of symbol
+/// indices, same as the secret number. Debug is derived so turns can be printed while debugging.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Turn {
+    /// The symbol indices of the guess
+    pub guess: Vec<u8>,
+    /// The answer of the game: symbols that are in the secret number, but at other positions
+    pub cows: u8,
+    /// And symbols that are at the same positions
+    pub bulls: u8,
+    /// The moment the guess was scored
+    pub time: SystemTime,
+}
+
+/// Here is our game structure that contains data that we'll need to play. This definition is public
+/// so it can be called from the outside of this module.
 pub struct Game {
 
-    // This is a private definition of a fixed-length array of four elements that contain unsigned
-    // integers of 8 bit length. This variable will not be seen from outside this module.
-    secret_number: [u8; 4],
+    /// The rules of this game. It's public so the main program can tell how many symbols to ask
+    /// for, but since GameConfig fields are private, nobody can change the rules mid-game.
+    pub config: GameConfig,
+
+    // This is a private secret number (or a few of them, see the Secret enum above). A number is
+    // a vector of unsigned integers of 8 bit length. Each one is an index of a symbol in the
+    // alphabet of our config. We can't use a fixed-length array here anymore, because the length
+    // of the code is only known when the game is created. This variable will not be seen from
+    // outside this module.
+    secret: Secret,
 
-    // We'll keep the count of guess attempts in this public unsigned 32 bit integer
+    /// We'll keep the count of guess attempts in this public unsigned 32 bit integer
     pub tries: u32,
 
-    // This is a two-dimmensional array (10 lines, 4 columns) of Hint enum values. We'll store our
-    // estimations on potential digit positions here.
-    pub hint_table: [[Hint; 4]; 10],
+    /// This is a two-dimmensional table (one line per symbol, one column per position) of Hint enum
+    /// values. We'll store our estimations on potential symbol positions here.
+    pub hint_table: Vec<Vec<Hint>>,
+
+    /// Every scored guess in the order they were made. The solver uses it to know what the
+    /// player has already learned about the secret number.
+    pub history: Vec<Turn>,
+
+    /// The moment the game has started
+    pub started: SystemTime,
+
+    /// The seed the secret number was made from, if any. Games with the same seed and the same
+    /// rules have the same secret number.
+    pub seed: Option<u64>,
+
+    /// In hard mode every guess must agree with all earlier answers, see check_hard(). It's off
+    /// in a new game, turn it on right after creating one.
+    pub hard: bool,
 }
 
 // This is an implementation of our Game type. It stores methods and associated functions of our
 // game object.
 impl Game {
 
-    // This is a constructor. The "new" name is not special, but it is common to call standard
-    // object constructors like this. We need to specify that it is public to access it from
-    // outside the module. This is not an object method, because it doesn't have a reference to
-    // special variable called "self" in it's arguments list.
-    pub fn new() -> Game {
+    /// This is a constructor. The "new" name is not special, but it is common to call standard
+    /// object constructors like this. We need to specify that it is public to access it from
+    /// outside the module. This is not an object method, because it doesn't have a reference to
+    /// special variable called "self" in it's arguments list. The rules of the game are passed in,
+    /// use GameConfig::default() for the classic four digits. The seed is optional: without it
+    /// every game is different, with it the game can be played again with the same secret number.
+    pub fn new(config: GameConfig, seed: Option<u64>) -> Game {
         // So we construct an actual object
         Game {
             // We'll use our own function that randomizes the secret number. See below for details.
-            secret_number: Game::generate_secret(),
+            secret: Secret::Fixed(Arc::new(Game::generate_secret(&config, seed))),
 
             // We start with zero guess attempts at the beginning of the game
             tries: 0,
 
-            // Here we initialize our hint table. This syntax makes sure that whole 10x4 table of
-            // Hint typed values being filled with Hint::Unknown values.
-            hint_table: [[Hint::Unknown; 4]; 10],
-        }
-    }
+            // Here we initialize our hint table. The vec! macro works like the array syntax, so
+            // this makes sure that the whole table is filled with Hint::Unknown values.
+            hint_table: vec![vec![Hint::Unknown; config.length()]; config.symbols().len()],
 
-    // This is a public method (see the &self argument?), which purpose is to check whether a
-    // supplemented string contains a secret number. It returns bool value as a result.
-    pub fn guess(&self, variant: &str) -> bool {
+            // Nothing was guessed yet, so the history is an empty vector
+            history: Vec::new(),
 
-        // First, we want to parse the string to our inner representation of number, which is an
-        // array of four u8s
-        let input = Game::from_string(variant);
+            // The clock starts now
+            started: SystemTime::now(),
 
-        // Loop through both secret number array and input array
-        for i in 0..4 {
-            // If some position is different between the two, we immediately return with a false
-            if self.secret_number[i] != input[i] {
-                return false
-            }
-        }
+            seed,
+
+            hard: false,
 
-        // If we've looped through the whole array and haven't got a false, then we're safe to
-        // assume that the input totally matches our secret number. Yay, we've won! Also, note that
-        // we don't use return operator. This is because whole our function body is an expression
-        // and last line of an expression becomes it's final result. Just make sure not to put a
-        // semicolon at the end of this line so Rust will know that it should be returned.
-        true
+            // The config is moved into the game last, because we've borrowed it above
+            config,
+        }
     }
 
-    // We'll use this method to look up digit positions of our number and tell our player about
-    // found cows and bulls
-    pub fn try(&mut self, variant: &str) -> (u8, u8) {
+    /// Creates a game with a secret number made up by someone else, like the other player of
+    /// a duel. The secret is a guess, so it's already known to be valid for the rules.
+    pub fn with_secret(config: GameConfig, secret: &Guess) -> Game {
+        let mut game = Game::new(config, None);
+        game.secret = Secret::Fixed(Arc::new(secret.code().to_vec()));
+        game
+    }
 
-        // First, we want to parse the string to our inner representation of number, which is an
-        // array of four u8s
-        let input = Game::from_string(variant);
+    /// Starts another game with the same secret number, for another player of a race. The game
+    /// is split in two parts: the secret number is shared, and everything else (the tries, the
+    /// history and the hint table) is the player's own and starts from scratch. The evil host
+    /// changes its mind with every answer, so it can't be shared and we return None for it.
+    pub fn share_secret(&self) -> Option<Game> {
+        match self.secret {
+            Secret::Fixed(ref number) => {
+                let mut game = Game::new(self.config.clone(), self.seed);
+                // Cloning an Arc doesn't copy the number, it only adds one more owner
+                game.secret = Secret::Fixed(number.clone());
+                game.hard = self.hard;
+                Some(game)
+            },
+            Secret::Evil(_) => None,
+        }
+    }
 
-        // Define mutable integers to count the cows and bulls
-        let mut cows = 0;
-        let mut bulls = 0;
+    /// This is a public method (see the &self argument?), which purpose is to check whether a
+    /// supplemented guess is the secret number. It returns bool value as a result. The guess was
+    /// parsed already (see the guess module), so there's nothing that can go wrong here.
+    pub fn guess(&self, variant: &Guess) -> bool {
+
+        // Vectors and slices can be compared with == operator directly. If every position is the
+        // same in both, then we're safe to assume that the input totally matches our secret number.
+        // Yay, we've won! The evil host only gives up when the guess is the last number it has
+        // left. Also, note that we don't use return operator. This is because whole our function
+        // body is an expression and last line of an expression becomes it's final result. Just
+        // make sure not to put a semicolon at the end of this line so Rust will know that it
+        // should be returned.
+        match self.secret {
+            Secret::Fixed(ref number) => number.as_slice() == variant.code(),
+            Secret::Evil(ref numbers) => numbers.len() == 1 && numbers[0] == variant.code(),
+        }
+    }
 
-        // Loop through four digits
-        for i in 0..4 {
+    /// Tells the secret number, for example to write it into a transcript of a finished game. The
+    /// evil host only has a secret number when it has one number left, otherwise we get None.
+    pub fn reveal(&self) -> Option<&[u8]> {
+        match self.secret {
+            Secret::Fixed(ref number) => Some(number),
+            Secret::Evil(ref numbers) if numbers.len() == 1 => Some(&numbers[0]),
+            Secret::Evil(_) => None,
+        }
+    }
 
-            // For every two digits matched between the input and secret arrays, we add a bull
-            if self.secret_number[i] == input[i] {
-                bulls += 1;
-            }
+    /// We'll use this method to look up digit positions of our number and tell our player about
+    /// found cows and bulls
+    pub fn try(&mut self, variant: &Guess) -> (u8, u8) {
 
-            // Crossloop through two arrays to find existing digits that don't match positions to
-            // count cows
-            for j in 0..4 {
-                if i != j && self.secret_number[i] == input[j] {
-                    cows += 1;
-                }
-            }
-        }
+        // Counting is done by the score() function below, so anyone can score two codes
+        // without having a game at hand. The evil host picks the answer that suits it best.
+        let (cows, bulls) = match self.secret {
+            Secret::Fixed(ref number) => score(number, variant.code()),
+            Secret::Evil(ref mut numbers) => evil::answer(numbers, variant.code()),
+        };
 
-        // Register that we tried another guess
+        // Register that we tried another guess and remember how it went. The history keeps its
+        // own copy of the guess, so we make one with to_vec().
         self.tries += 1;
+        self.history.push(Turn { guess: variant.code().to_vec(), cows, bulls, time: SystemTime::now() });
 
         // Return a tuple of cows and bulls
         (cows, bulls)
     }
 
-    // This method uses simple heuristics to add digit position hints to our respective table. Note
-    // that it doesn't use the secret number and all assumptions that can be made inside it can be
-    // made by player using logic and a piece of paper (or a good memory).
-    pub fn analyze(&mut self, variant: &str, cows: u8, bulls: u8) {
+    /// Plays one move: scores the guess like try() does and tells whether it has won the game.
+    /// Every frontend plays guesses this way, so the winning guess is scored and kept in the
+    /// history like any other, and its answer is all bulls.
+    pub fn play(&mut self, variant: &Guess) -> (u8, u8, bool) {
+        // The check has to come first, because the evil host drops numbers while it answers
+        let won = self.guess(variant);
+        let (cows, bulls) = self.try(variant);
+        (cows, bulls, won)
+    }
 
-        // Again, parse string to array
-        let input = Game::from_string(variant);
+    /// This method updates the hint table with everything we've learned so far. It doesn't use
+    /// the secret number, only the history of guesses and answers, so all conclusions it makes can
+    /// be made by player using logic and a piece of paper (or a good memory). See the hints module
+    /// for the details.
+    pub fn analyze(&mut self) {
+        self.hint_table = hints::hint_table(&self.config, &self.history);
+    }
 
-        // First case is most useful. When there are no cows or bulls, we can be sure that the
-        // secret number does not contain any digit from our guess.
-        if cows == 0 && bulls == 0 {
+    // Private function to generate random sequence of symbols for the given rules. Without a seed
+    // we use the generator of the current thread, which is seeded by the operating system. With
+    // a seed we make our own generator, which gives the same sequence of numbers for the same seed
+    // on every computer.
+    fn generate_secret(config: &GameConfig, seed: Option<u64>) -> Vec<u8> {
+        match seed {
+            None => Game::random_code(config, &mut rand::thread_rng()),
+            Some(seed) => Game::random_code(config, &mut seeded_rng(seed)),
+        }
+    }
 
-            // For every for input digits...
-            for v in &input {
+    // Makes a random code with any generator. The generator is a type parameter, because the two
+    // generators above are of different types, and both implement the Rng trait.
+    fn random_code<R: Rng>(config: &GameConfig, rng: &mut R) -> Vec<u8> {
+        let symbols = config.symbols().len();
+
+        // When symbols can repeat, every position simply gets a random symbol. With 256 symbols
+        // every u8 is a symbol, and the range can't be written in u8, so we take any u8 at all.
+        if config.repeats() {
+            return (0..config.length())
+                .map(|_| if symbols == MAX_SYMBOLS { rng.gen() } else { rng.gen_range(0, symbols as u8) })
+                .collect();
+        }
 
-                // ..we loop through four available positions in secret number...
-                for j in 0..4 {
+        // Create a vector of all symbol indices. The collect() consumer builds it from a range.
+        let mut array = (0..symbols).map(|symbol| symbol as u8).collect::<Vec<u8>>();
 
-                    // ...and set a hint Hint::NotHere
-                    self.hint_table[*v as usize][j] = Hint::NotHere;
+        // Randomly shuffle the vector using the generator
+        rng.shuffle(&mut array);
 
-                    // Note the funny "*v as usize" construct. Since every element of an input
-                    // array is represented by a reference, we should dereference it to simple u8
-                    // and only then cast it as special usize type that is used for array indices.
-                    // And since j is just a generic int (and Rust is not sure about it's exact
-                    // type at this moment), we can ommit type casting to usize, because Rust is
-                    // smart enough to do that for us.
-                }
-            }
-        }
+        // Drop everything past the code length and return what's left
+        array.truncate(config.length());
+        array
+    }
+}
 
-        // Another useful case is when a sum of cows and bulls is four. That means, that every
-        // digit of a secret number is represented in the guess.
-        if cows + bulls == 4 {
-            // So we loop through all 10 possible digits from 0 to 9
-            for i in 0..10 {
-
-                // We check if this particular digit is mentioned in the guess
-                let mut is_present = false;
-                for v in &input {
-
-                    // Remember, when we loop through the array, we get references to cells, not
-                    // their value. So we must dereference it to compare with common integer.
-                    // At this point, undefined int i is being compare with a definite u8, so Rust
-                    // thinks "Aha! This i one must be also u8!" and from this point treats it like
-                    // a u8.
-                    if i == *v {
-                        is_present = true;
-                    }
-                }
-
-                // So, this digit i is not present in the input number
-                if !is_present {
-                    // We run through every possible position for this digit and mark it as not
-                    // possible
-                    for j in 0..4 {
-                        // Since i was compared to v earilier, we must type cast it to usize so
-                        // it can be used as an array index
-                        self.hint_table[i as usize][j] = Hint::NotHere;
-                    }
-                }
-            }
-        }
+// Makes a random number generator from a seed. XorShiftRng wants four 32-bit numbers, which must
+// not be all zeros, so we spread our 64-bit seed over them with a few rounds of the SplitMix64
+// mixer, and nudge the first one in the unlikely case they all are zeros anyway.
+fn seeded_rng(seed: u64) -> XorShiftRng {
+    let mut state = seed;
+    let mut words = [0u32; 4];
+    for word in words.iter_mut() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut mixed = state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        *word = (mixed ^ (mixed >> 31)) as u32;
+    }
+    if words == [0; 4] {
+        words[0] = 1;
+    }
+    XorShiftRng::from_seed(words)
+}
 
-        // But what can we think of when there are some bulls in the guess? We can suspect every
-        // digit of the guess to be at it's position.
-        if bulls > 0 {
-            for i in 0..4 {
-                // Note that when we initialize the new binding from an array cell, we don't need
-                // to dereference it as it was when we looped through input using for .. in
-                let v = input[i] as usize;
-
-                // For every previously unknown position we can assume that maybe (just maybe!)
-                // this digit could be here
-                if self.hint_table[v][i] == Hint::Unknown {
-                    self.hint_table[v][i] = Hint::Maybe;
-                }
-            }
+/// This function counts cows and bulls of a guess against a secret. It's not a method of the Game,
+/// because the solver needs to score codes that are not secret at all: if the secret was X, what
+/// would the player hear for a guess Y? Slices (&[u8]) let us pass vectors of any length.
+///
+/// Every symbol of the secret can be matched only once. With repeated symbols this matters: for
+/// the secret 1123 the guess 1111 has two bulls and no cows, because both ones of the secret are
+/// taken by the bulls already.
+///
+/// Both codes must be of the same length, it makes no sense to score them otherwise. The function
+/// panics if they are not, so a bug in the caller doesn't go unnoticed.
+pub fn score(secret: &[u8], guess: &[u8]) -> (u8, u8) {
+    assert_eq!(secret.len(), guess.len(), "the secret and the guess must be of the same length");
+
+    // Define mutable integers to count the cows and bulls
+    let mut cows = 0;
+    let mut bulls = 0;
+
+    // We remember which positions of the secret are matched already as bits of a number: bit i
+    // is set when position i is taken. Codes are never longer than 32 symbols, so u32 is enough.
+    let mut taken = 0u32;
+
+    // Bulls go first, because a symbol at the right place is always a bull, even if it could
+    // also be a cow for some other position. The zip() adapter walks both codes side by side.
+    for (i, (secret, symbol)) in secret.iter().zip(guess.iter()).enumerate() {
+        if secret == symbol {
+            bulls += 1;
+            taken |= 1 << i;
         }
+    }
 
-        // Another useful case is when every match we have is a bull. We can use previously known
-        // hints to calculate some positions of a guess.
-        if cows == 0 && bulls > 0 {
-            // Loop through input digits and count how many of them are definitely not on their
-            // positions for this guess
-            let mut c = 0;
-            for i in 0..4 {
-                let v = input[i] as usize;
-                if self.hint_table[v][i] == Hint::NotHere {
-                    c += 1;
-                }
-            }
-
-            // If the sum of found bulls plus the sum of "definitely not here" digits is four, we
-            // can assume, that every previously unknown positioned digits are at their right
-            // positions now
-            if c + bulls == 4 {
-                for i in 0..4 {
-                    let v = input[i] as usize;
-                    if self.hint_table[v][i] == Hint::Unknown {
-                        self.hint_table[v][i] == Hint::Here;
-                    }
-                }
-            }
+    // Then every other symbol of the guess looks for the same symbol at a position of the secret
+    // that is not taken yet. If there is one, it's a cow, and that position is taken now.
+    for (i, symbol) in guess.iter().enumerate() {
+        if secret[i] == *symbol {
+            continue;
         }
 
-        // And the last case. When we have exclusively cows. That means that none of mentioned
-        // digits are at their positions this time.
-        else if cows > 0 && bulls == 0 {
-            for i in 0..4 {
-                let v = input[i] as usize;
-
-                // For every position that was unclear previously, we mark it as definitely "no"
-                if self.hint_table[v][i] == Hint::Maybe || self.hint_table[v][i] == Hint::Unknown {
-                    self.hint_table[v][i] = Hint::NotHere;
-                }
-            }
+        let free = secret.iter()
+            .enumerate()
+            .position(|(j, other)| taken & (1 << j) == 0 && other == symbol);
+        if let Some(j) = free {
+            cows += 1;
+            taken |= 1 << j;
         }
     }
 
-    // This method is used to check whether a proposed number consists of unique digits or has
-    // duplicates
-    pub fn check_unique_digits(&self, variant: &str) -> bool {
-
-        // As usual, parse the string to array
-        let input = Game::from_string(variant);
+    (cows, bulls)
+}
 
-        // Crossloop through this array with itself to find duplicates
-        for i in 0..4 {
-            for j in (i + 1)..4 {
-                if input[i] == input[j] {
-                    return false;
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::{Alphabet, Game, GameConfig, score};
 
-        true
+    #[test]
+    fn unique_symbols() {
+        assert_eq!(score(&[1, 2, 3, 4], &[1, 2, 3, 4]), (0, 4));
+        assert_eq!(score(&[1, 2, 3, 4], &[4, 3, 2, 1]), (4, 0));
+        assert_eq!(score(&[1, 2, 3, 4], &[1, 3, 5, 6]), (1, 1));
+        assert_eq!(score(&[1, 2, 3, 4], &[5, 6, 7, 8]), (0, 0));
     }
 
-    // Private function to generate random sequence of four unique decimal digits
-    fn generate_secret() -> [u8; 4] {
-        // Init the random number generator
-        let mut rng = rand::thread_rng();
+    // The example from the doc comment: both ones of the secret are taken by the bulls
+    #[test]
+    fn repeats_in_the_guess() {
+        assert_eq!(score(&[1, 1, 2, 3], &[1, 1, 1, 1]), (0, 2));
+        assert_eq!(score(&[0, 1, 2, 3], &[0, 0, 0, 0]), (0, 1));
+        assert_eq!(score(&[0, 1, 2, 3], &[1, 1, 1, 1]), (0, 1));
+        assert_eq!(score(&[0, 1, 2, 3], &[3, 3, 0, 0]), (2, 0));
+    }
 
-        // Create an array of decimal digits
-        let mut array = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+    // A symbol of the guess can't take a position of the secret that another one has taken
+    #[test]
+    fn repeats_in_the_secret() {
+        assert_eq!(score(&[0, 0, 1, 1], &[1, 1, 0, 0]), (4, 0));
+        assert_eq!(score(&[0, 0, 1, 2], &[0, 3, 0, 0]), (1, 1));
+        assert_eq!(score(&[0, 0, 0, 1], &[1, 0, 2, 2]), (1, 1));
+        assert_eq!(score(&[2, 2, 2, 2], &[2, 2, 2, 2]), (0, 4));
+    }
 
-        // Randomly shuffle the array using the generator
-        rng.shuffle(&mut array);
+    // Scoring doesn't care which code is the secret and which is the guess
+    #[test]
+    fn symmetric() {
+        let codes = [[0, 0, 1, 1], [1, 0, 2, 2], [0, 1, 2, 3], [3, 3, 3, 0]];
+        for a in &codes {
+            for b in &codes {
+                assert_eq!(score(a, b), score(b, a));
+            }
+        }
+    }
 
-        // Return first four digits of resulting array
-        [ array[0], array[1], array[2], array[3] ]
+    #[test]
+    #[should_panic]
+    fn different_lengths() {
+        score(&[0, 1, 2], &[0, 1, 2, 3]);
     }
 
-    // Private function that parses the string to an array of digits
-    fn from_string(value: &str) -> [u8; 4] {
-        // Create a mutable array and populate it with four zeroes
-        let mut array = [0u8; 4];
-
-        // Use chars() method to create an iterator over every character of the string. Then for
-        // every iterated char value covert them to string and parse the string to u8 type. The
-        // result of String::parse() method has a Result type, so we must unwrap it to get an
-        // actual value. We have to assume that this Result is always successfull for this.
-        // Otherwise, it'll throw a non-intercepted exception and the application will crash
-        // horribly. After the mapping procedure, we will receive a collection object that must
-        // be "consumed", as they call it here in Rust. We use the collect() consumer to wrap the
-        // data into a vector of u8 integers.
-        let input = value
-            .chars()
-            .map( |x| x.to_string().parse::<u8>().unwrap() )
-            .collect::<Vec<u8>>();
-
-        // Then we loop through array indexes and assign them the values of the vector that we've
-        // got above. We use get() to access the vector value of particular index, then unwrap the
-        // Result which gives us the reference to desired value, which, in turn, we dereference to
-        // an actual number.
-        for i in 0..4 {
-            array[i] = *input.get(i).unwrap();
-        }
+    // A seed must always make the same secret, or saved and daily games would change under the
+    // players. The number for 42 is pinned, so a new version of rand can't slip by either.
+    fn secret(config: &GameConfig, seed: u64) -> Vec<u8> {
+        Game::new(config.clone(), Some(seed)).reveal().unwrap().to_vec()
+    }
 
-        // That is all, return generated array
-        array
+    #[test]
+    fn seeded_secret() {
+        let config = GameConfig::default();
+        assert_eq!(secret(&config, 42), [5, 7, 8, 0]);
+        assert_eq!(secret(&config, 42), secret(&config, 42));
+        let hex = GameConfig::with_repeats(6, Alphabet::Hex, true).unwrap();
+        assert_eq!(secret(&hex, 7), secret(&hex, 7));
+        assert!(hex.is_valid_code(&secret(&hex, 7)));
     }
 }
@@ -0,0 +1,397 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module saves a game to a file and loads it back. The file is plain text, one record per
+//! line, so it's easy to look at and easy to parse:
+//!
+//! ```text
+//! cowbull-save 2
+//! rules 4 digits
+//! secret 9c41e07a
+//! started 1435400000
+//! seed 42
+//! turn 0123 1 1 1435400012
+//! turn 4567 1 0 1435400031
+//! ```
+//!
+//! The first line tells what the file is and which version of the format it uses, so we can
+//! change the format later and still recognize old files. The rules are written the same way as
+//! in the other files of the game (see the format module). Version 1 had them on separate lines
+//! instead: `length 4`, `alphabet digits` or `alphabet custom <symbols>` and `repeats yes`, and we
+//! still read those. The seed line is only written for seeded games and a line `hard yes` only for
+//! hard mode. Times are seconds since 1970, also known as Unix time. The secret number is
+//! scrambled, so a quick look at the file doesn't spoil the game. It's not a real encryption,
+//! anyone who reads this code can unscramble it. The evil host has no secret number to save, so
+//! its games have a line `evil yes` instead and the numbers it still keeps are found again from
+//! the answers.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{Alphabet, Game, GameConfig, Secret, Turn, score};
+use super::format::{HeaderError, check_header, parse_number, parse_rules, rules_words, write_header};
+use solver::all_codes;
+
+// The first word of every save file
+const MAGIC: &str = "cowbull-save";
+
+/// The version of the format we write
+pub const SAVE_VERSION: u32 = 2;
+
+// The oldest version we can still read
+const OLDEST_VERSION: u32 = 1;
+
+/// Things that can go wrong while saving or loading
+#[derive(Debug)]
+pub enum SaveError {
+    /// The file can't be read or written
+    Io(io::Error),
+    /// The file was saved by a version of the game we can't read
+    Version(u32),
+    /// Something is wrong on a line of the file
+    Format(usize, String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SaveError::Io(ref error) => write!(f, "{}", error),
+            SaveError::Version(version) =>
+                write!(f, "Save format version {} is not supported, version {} is the latest", version, SAVE_VERSION),
+            // Line zero means the whole file, for example when a record is missing
+            SaveError::Format(0, ref message) => write!(f, "Bad save file: {}", message),
+            SaveError::Format(line, ref message) => write!(f, "Bad save file, line {}: {}", line, message),
+        }
+    }
+}
+
+// The From trait lets the question mark operator turn an io::Error into a SaveError for us
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> SaveError {
+        SaveError::Io(error)
+    }
+}
+
+impl Game {
+
+    /// Writes the game to anything that implements the Write trait: a file, a network connection
+    /// or a vector of bytes in memory
+    pub fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let started = unix_time(self.started);
+
+        write_header(out, MAGIC, SAVE_VERSION)?;
+        writeln!(out, "rules {}", rules_words(&self.config))?;
+        match self.secret {
+            Secret::Fixed(ref number) => writeln!(out, "secret {}", scramble(number, started))?,
+            Secret::Evil(_) => writeln!(out, "evil yes")?,
+        }
+        writeln!(out, "started {}", started)?;
+        if let Some(seed) = self.seed {
+            writeln!(out, "seed {}", seed)?;
+        }
+        if self.hard {
+            writeln!(out, "hard yes")?;
+        }
+        for turn in &self.history {
+            writeln!(out, "turn {} {} {} {}",
+                     self.config.format_code(&turn.guess), turn.cows, turn.bulls, unix_time(turn.time))?;
+        }
+        Ok(())
+    }
+
+    /// Reads a game from anything that can be read line by line. Every answer in the file is
+    /// checked against the secret number, so a damaged file can't give us a broken game.
+    pub fn load<R: BufRead>(input: R) -> Result<Game, SaveError> {
+        let mut version = SAVE_VERSION;
+        let mut config = None;
+        // Version 1 had the rules on three lines
+        let mut length = None;
+        let mut alphabet = None;
+        let mut repeats = false;
+        let mut secret = None;
+        let mut evil = false;
+        let mut started = None;
+        let mut seed = None;
+        let mut hard = false;
+        let mut turns = Vec::new();
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let number = i + 1;
+
+            // The first line must be our header
+            if number == 1 {
+                match check_header(&line, MAGIC, OLDEST_VERSION..=SAVE_VERSION) {
+                    Ok(found) => {
+                        version = found;
+                        continue;
+                    },
+                    Err(HeaderError::Version(version)) => return Err(SaveError::Version(version)),
+                    Err(HeaderError::BadVersion) => return Err(format_error(number, "bad version")),
+                    Err(HeaderError::Foreign) => return Err(format_error(number, "this is not a saved game")),
+                }
+            }
+
+            // Split the line into a key and the rest of it
+            let mut words = line.split_whitespace();
+            let key = words.next();
+            let values = words.collect::<Vec<&str>>();
+
+            match (key, values.as_slice()) {
+                // Empty lines don't hurt anybody
+                (None, _) => (),
+                (Some("rules"), [length, alphabet, flags @ ..]) if version > 1 && flags.iter().all(|&flag| flag == "repeats") =>
+                    config = Some(parse_rules(length, alphabet, flags).map_err(|message| format_error(number, &message))?),
+                (Some("length"), [value]) if version == 1 => length = Some(parse(number, value)?),
+                (Some("alphabet"), ["custom", symbols]) if version == 1 => alphabet = Some(Alphabet::Custom(symbols.chars().collect())),
+                (Some("alphabet"), [name]) if version == 1 => alphabet = Some(Alphabet::from_name(name)),
+                (Some("repeats"), ["yes"]) if version == 1 => repeats = true,
+                (Some("secret"), [value]) => secret = Some((number, value.to_string())),
+                (Some("evil"), ["yes"]) => evil = true,
+                (Some("started"), [value]) => started = Some(parse(number, value)?),
+                (Some("seed"), [value]) => seed = Some(parse(number, value)?),
+                (Some("hard"), ["yes"]) => hard = true,
+                (Some("turn"), [guess, cows, bulls, time]) =>
+                    turns.push((number, guess.to_string(), parse(number, cows)?, parse(number, bulls)?, parse(number, time)?)),
+                _ => return Err(format_error(number, "unknown record")),
+            }
+        }
+
+        // Every record but turns must be present. The ok_or_else() turns an Option into a Result.
+        let config = match version {
+            1 => {
+                let length = length.ok_or_else(|| format_error(0, "code length is missing"))?;
+                let alphabet = alphabet.ok_or_else(|| format_error(0, "alphabet is missing"))?;
+                GameConfig::with_repeats(length, alphabet, repeats).map_err(|error| format_error(0, &error.to_string()))?
+            },
+            _ => config.ok_or_else(|| format_error(0, "rules are missing"))?,
+        };
+        let started = started.ok_or_else(|| format_error(0, "start time is missing"))?;
+
+        // Unscramble the secret number and make sure it's valid for these rules. The evil host
+        // starts with every number again, and the answers below will drop the ones it has lost.
+        let mut secret = match (secret, evil) {
+            (Some(_), true) => return Err(format_error(0, "the evil host can't have a secret")),
+            (Some((secret_line, secret)), false) => Secret::Fixed(Arc::new(unscramble(&secret, started)
+                .filter(|code| config.is_valid_code(code))
+                .ok_or_else(|| format_error(secret_line, "the secret is damaged"))?)),
+            (None, true) => Secret::Evil(all_codes(&config).map_err(|error| format_error(0, &error.to_string()))?),
+            (None, false) => return Err(format_error(0, "secret is missing")),
+        };
+
+        // Parse the guesses and check that every answer is what the game would have said
+        let mut history = Vec::new();
+        for (number, guess, cows, bulls, time) in turns {
+            let guess = guess.chars()
+                .map(|symbol| config.index_of(symbol))
+                .collect::<Option<Vec<u8>>>()
+                .filter(|code| config.is_valid_code(code))
+                .ok_or_else(|| format_error(number, "bad guess"))?;
+            match secret {
+                Secret::Fixed(ref secret) => if score(secret, &guess) != (cows, bulls) {
+                    return Err(format_error(number, "the answer doesn't match the secret"));
+                },
+                Secret::Evil(ref mut numbers) => {
+                    numbers.retain(|code| score(code, &guess) == (cows, bulls));
+                    if numbers.is_empty() {
+                        return Err(format_error(number, "the answer doesn't match the earlier ones"));
+                    }
+                },
+            }
+            history.push(Turn { guess, cows, bulls, time: from_unix_time(time) });
+        }
+
+        // Now we can build the game and let it figure out the hints again
+        let mut game = Game {
+            tries: history.len() as u32,
+            hint_table: Vec::new(),
+            history,
+            started: from_unix_time(started),
+            seed,
+            hard,
+            secret,
+            config,
+        };
+        game.analyze();
+        Ok(game)
+    }
+
+    /// Saves the game to a file. We write to a temporary file first and then rename it, so if the
+    /// program crashes halfway, the old save is still there.
+    pub fn save_file(&self, path: &Path) -> Result<(), SaveError> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        {
+            // The file is closed when it goes out of scope at the end of this block
+            let mut file = File::create(&temporary)?;
+            self.save(&mut file)?;
+            file.sync_all()?;
+        }
+
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    /// Loads a game from a file
+    pub fn load_file(path: &Path) -> Result<Game, SaveError> {
+        let file = File::open(path)?;
+        Game::load(BufReader::new(file))
+    }
+}
+
+// Makes a format error for a line
+fn format_error(line: usize, message: &str) -> SaveError {
+    SaveError::Format(line, message.to_string())
+}
+
+// Parses a number from a file, reporting the line if it's not a number
+fn parse<T: ::std::str::FromStr>(line: usize, value: &str) -> Result<T, SaveError> {
+    parse_number(value).map_err(|message| format_error(line, &message))
+}
+
+/// Converts a moment of time to seconds since 1970. Times before that are not worth the trouble.
+pub fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+/// Converts seconds since 1970 back to a moment of time
+pub fn from_unix_time(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+// Mixes the secret with a stream of pseudo-random bytes and writes it as hexadecimal numbers.
+// The stream depends on the start time of the game, so the same secret looks different in
+// different games. Doing the same thing twice gives the original bytes back.
+fn scramble(code: &[u8], started: u64) -> String {
+    code.iter()
+        .zip(key_stream(started))
+        .map(|(symbol, key)| format!("{:02x}", symbol ^ key))
+        .collect()
+}
+
+// Turns the hexadecimal string back into the secret, or None if it's not hexadecimal
+fn unscramble(value: &str, started: u64) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) || !value.is_ascii() {
+        return None;
+    }
+
+    (0..value.len() / 2)
+        .zip(key_stream(started))
+        .map(|(i, key)| u8::from_str_radix(&value[i * 2..i * 2 + 2], 16).ok().map(|byte| byte ^ key))
+        .collect()
+}
+
+// A simple linear congruential generator, the kind of random numbers you find in old C libraries.
+// It's predictable, which is exactly what we need to unscramble the secret later.
+// The "impl Iterator" return type means we return some iterator without spelling out its type.
+fn key_stream(seed: u64) -> impl Iterator<Item = u8> {
+    let mut state = seed ^ 0x0005_deec_e66d;
+    (0..).map(move |_: u64| {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (state >> 56) as u8
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SaveError, unix_time};
+    use game::{Alphabet, Game, GameConfig, Guess};
+
+    // Plays the guesses, saves the game to memory and loads it back
+    fn round_trip(mut game: Game, guesses: &[&str]) -> (Game, Game) {
+        for guess in guesses {
+            let guess = Guess::parse(guess, &game.config).unwrap();
+            game.try(&guess);
+        }
+        let mut file = Vec::new();
+        game.save(&mut file).unwrap();
+        let loaded = Game::load(&file[..]).unwrap();
+        (game, loaded)
+    }
+
+    // Everything the file keeps must come back the same. Times are kept in whole seconds.
+    fn assert_same(game: &Game, loaded: &Game) {
+        assert_eq!(loaded.config, game.config);
+        assert_eq!(loaded.seed, game.seed);
+        assert_eq!(loaded.hard, game.hard);
+        assert_eq!(loaded.is_evil(), game.is_evil());
+        assert_eq!(loaded.reveal(), game.reveal());
+        assert_eq!(loaded.tries, game.tries);
+        assert_eq!(unix_time(loaded.started), unix_time(game.started));
+        assert_eq!(loaded.history.len(), game.history.len());
+        for (loaded, turn) in loaded.history.iter().zip(&game.history) {
+            assert_eq!((&loaded.guess, loaded.cows, loaded.bulls), (&turn.guess, turn.cows, turn.bulls));
+            assert_eq!(unix_time(loaded.time), unix_time(turn.time));
+        }
+    }
+
+    #[test]
+    fn seeded_game() {
+        let mut game = Game::new(GameConfig::default(), Some(42));
+        game.hard = true;
+        let (game, loaded) = round_trip(game, &["0123", "1456"]);
+        assert_same(&game, &loaded);
+    }
+
+    #[test]
+    fn custom_symbols_with_repeats() {
+        let config = GameConfig::with_repeats(5, Alphabet::Custom("xyzdigts".chars().collect()), true).unwrap();
+        let (game, loaded) = round_trip(Game::new(config, None), &["xxyyz", "digts"]);
+        assert_same(&game, &loaded);
+    }
+
+    #[test]
+    fn evil_game() {
+        let game = Game::evil(GameConfig::new(3, Alphabet::Digits).unwrap()).unwrap();
+        let (game, loaded) = round_trip(game, &["012", "345"]);
+        assert_same(&game, &loaded);
+    }
+
+    #[test]
+    fn changed_answer_is_rejected() {
+        let (game, _) = round_trip(Game::new(GameConfig::default(), Some(42)), &["0123"]);
+        let mut file = Vec::new();
+        game.save(&mut file).unwrap();
+
+        // The seed 42 answers 0123 with one cow and no bulls
+        let text = String::from_utf8(file).unwrap().replace("turn 0123 1 0", "turn 0123 0 1");
+        match Game::load(text.as_bytes()) {
+            Err(SaveError::Format(line, _)) => assert!(line > 1),
+            _ => panic!("a changed answer was accepted"),
+        }
+    }
+
+    #[test]
+    fn version_one_is_still_read() {
+        let config = GameConfig::with_repeats(4, Alphabet::Custom("abc".chars().collect()), true).unwrap();
+        let (game, _) = round_trip(Game::new(config, Some(7)), &["aabc"]);
+        let mut file = Vec::new();
+        game.save(&mut file).unwrap();
+
+        // Version 1 wrote the rules on three lines
+        let text = String::from_utf8(file).unwrap()
+            .replace("cowbull-save 2", "cowbull-save 1")
+            .replace("rules 4 custom:abc repeats", "length 4\nalphabet custom abc\nrepeats yes");
+        assert_same(&game, &Game::load(text.as_bytes()).unwrap());
+
+        // And a version 2 file can't use them
+        let text = text.replace("cowbull-save 1", "cowbull-save 2");
+        assert!(Game::load(text.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        match Game::load("cowbull-save 3\n".as_bytes()) {
+            Err(SaveError::Version(3)) => (),
+            _ => panic!("a newer version was accepted"),
+        }
+        assert!(Game::load("cowbull-stats 1\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,557 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module keeps the leaderboard of seeded games. Everybody who plays the same seed guesses the
+//! same number (the daily puzzle is just a seed made from the date), so their tries can be compared
+//! fairly. Players are ranked by tries, and by time when the tries are equal.
+//!
+//! The leaderboard is a plain text file, one won game per line:
+//!
+//! ```text
+//! cowbull-leaderboard 1
+//! entry 1435400100 93 alice 42 01231456257837855780 4 digits
+//! entry 1435400300 71 - 42 0123145625783785 4 digits hard
+//! ```
+//!
+//! A line tells when the game was won (in Unix time), how long it took in seconds, who has won it
+//! (a dash for a player without a profile), the seed, every guess one after another, and the
+//! rules, like in the stats file. A text file is easy to change by hand, so an entry doesn't just
+//! say how many tries it took. It has the guesses, and we play them again against the secret
+//! number made from the seed. If they don't lead to a win in exactly that many tries, the entry is
+//! left out. Nobody can type a low score into the file without also finding the guesses that make
+//! it. The time can't be checked this way, so the time only decides between equal tries.
+//!
+//! Once a puzzle is solved, its number is no secret to the player anymore, so only the first win
+//! of every player on every seed is ranked. Playing the same seed again doesn't improve the score.
+//! Players without a profile can't be told apart, so they are ranked as one anonymous player. The
+//! player can also see the number without winning: by giving the game up, by letting the computer
+//! solve it or by exporting its transcript. Then a `spoiled` line is written, and the later wins
+//! of the player on that seed are not ranked either:
+//!
+//! ```text
+//! spoiled 1435400500 alice 43 4 digits
+//! ```
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use game::{Game, GameConfig, Guess, from_unix_time, unix_time};
+use game::format::{check_header, parse_rules, rules_words, write_header};
+use game::daily::day_number;
+
+// The first word of a leaderboard file
+const MAGIC: &str = "cowbull-leaderboard";
+
+/// The version of the format we write
+pub const LEADERBOARD_VERSION: u32 = 1;
+
+/// The name written for players without a profile
+pub const ANONYMOUS: &str = "-";
+
+/// One won seeded game
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// The name of the player's profile, or ANONYMOUS
+    pub player: String,
+    /// The moment the game was won
+    pub finished: SystemTime,
+    /// How long the game took
+    pub duration: Duration,
+    /// The seed of the secret number
+    pub seed: u64,
+    /// The rules of the game
+    pub config: GameConfig,
+    /// True if the game was played in hard mode
+    pub hard: bool,
+    /// Every guess of the game as symbol indices, the winning one last
+    pub guesses: Vec<Vec<u8>>,
+}
+
+impl Entry {
+
+    /// Makes an entry of a game that has just been won. Only seeded games can be compared, and
+    /// the winning guess must be in the history, so for others we get None.
+    pub fn from_game(game: &Game, player: &str) -> Option<Entry> {
+        let seed = game.seed?;
+        let length = game.config.length();
+        let won = game.history.last().is_some_and(|turn| turn.bulls as usize == length);
+        if !won || game.is_evil() {
+            return None;
+        }
+
+        let finished = SystemTime::now();
+        Some(Entry {
+            player: player.to_string(),
+            finished,
+            duration: finished.duration_since(game.started).unwrap_or_default(),
+            seed,
+            config: game.config.clone(),
+            hard: game.hard,
+            guesses: game.history.iter().map(|turn| turn.guess.clone()).collect(),
+        })
+    }
+
+    /// The number of tries, which is the number of guesses
+    pub fn tries(&self) -> usize {
+        self.guesses.len()
+    }
+
+    /// Plays the guesses again against the secret number made from the seed. Returns true if every
+    /// guess but the last one misses, the last one wins and, in hard mode, every guess agrees with
+    /// the earlier answers.
+    pub fn verify(&self) -> bool {
+        let mut game = Game::new(self.config.clone(), Some(self.seed));
+        game.hard = self.hard;
+
+        for (i, code) in self.guesses.iter().enumerate() {
+            let guess = match Guess::from_code(code.clone(), &self.config) {
+                Some(guess) => guess,
+                None => return false,
+            };
+            if game.check_hard(&guess).is_err() {
+                return false;
+            }
+            let (_, _, won) = game.play(&guess);
+            if won != (i + 1 == self.guesses.len()) {
+                return false;
+            }
+        }
+        !self.guesses.is_empty()
+    }
+
+    // Writes the entry as a line of the file
+    fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let guesses = self.guesses.iter()
+            .map(|guess| self.config.format_code(guess))
+            .collect::<String>();
+        let mut line = format!("entry {} {} {} {} {} {}", unix_time(self.finished),
+                               self.duration.as_secs(), self.player, self.seed, guesses,
+                               rules_words(&self.config));
+        if self.hard {
+            line.push_str(" hard");
+        }
+
+        // The whole line is written at once, like in the stats file
+        out.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    // Reads an entry from a line of the file, or None if the line is damaged
+    fn parse(line: &str) -> Option<Entry> {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            ["entry", finished, seconds, player, seed, guesses, length, alphabet, flags @ ..] => {
+                let config = parse_rules(length, alphabet, flags).ok()?;
+
+                // The guesses are written one after another, so we cut them by the code length.
+                // The chunks() method gives us slices of that many symbols.
+                let symbols = guesses.chars()
+                    .map(|symbol| config.index_of(symbol))
+                    .collect::<Option<Vec<u8>>>()?;
+                if !symbols.len().is_multiple_of(config.length()) {
+                    return None;
+                }
+                let guesses = symbols.chunks(config.length()).map(|guess| guess.to_vec()).collect();
+
+                Some(Entry {
+                    player: player.to_string(),
+                    finished: from_unix_time(finished.parse().ok()?),
+                    duration: Duration::from_secs(seconds.parse().ok()?),
+                    seed: seed.parse().ok()?,
+                    config,
+                    hard: flags.contains(&"hard"),
+                    guesses,
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A seed whose number a player has seen without winning the game
+#[derive(Clone, Debug)]
+pub struct Spoiler {
+    /// The name of the player's profile, or ANONYMOUS
+    pub player: String,
+    /// The moment the number was seen
+    pub time: SystemTime,
+    /// The seed of the secret number
+    pub seed: u64,
+    /// The rules of the game. The same seed makes another number with other rules.
+    pub config: GameConfig,
+}
+
+impl Spoiler {
+
+    /// Makes a spoiler of a game whose number the player has just seen. Only seeded games are on
+    /// the leaderboard, so for others we get None.
+    pub fn from_game(game: &Game, player: &str) -> Option<Spoiler> {
+        if game.is_evil() {
+            return None;
+        }
+        Some(Spoiler {
+            player: player.to_string(),
+            time: SystemTime::now(),
+            seed: game.seed?,
+            config: game.config.clone(),
+        })
+    }
+
+    // True if the entry is a win of the same player on the same puzzle
+    fn spoils(&self, entry: &Entry) -> bool {
+        self.player == entry.player && self.seed == entry.seed && self.config == entry.config
+    }
+
+    // Writes the spoiler as a line of the file. The hard mode doesn't matter, the number is the
+    // same with or without it.
+    fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let line = format!("spoiled {} {} {} {}", unix_time(self.time), self.player, self.seed,
+                           rules_words(&self.config));
+        out.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    // Reads a spoiler from a line of the file, or None if the line is damaged
+    fn parse(line: &str) -> Option<Spoiler> {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            ["spoiled", time, player, seed, length, alphabet, flags @ ..] => Some(Spoiler {
+                player: player.to_string(),
+                time: from_unix_time(time.parse().ok()?),
+                seed: seed.parse().ok()?,
+                config: parse_rules(length, alphabet, flags).ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Which games of the leaderboard to show
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Period {
+    /// Games won today, by the UTC calendar like the daily puzzle
+    Day,
+    /// Games won today or in the 6 days before
+    Week,
+    /// Every game
+    All,
+}
+
+impl Period {
+
+    /// Turns a word from the player into a period
+    pub fn from_name(name: &str) -> Option<Period> {
+        match name {
+            "day" | "today" => Some(Period::Day),
+            "week" => Some(Period::Week),
+            "all" => Some(Period::All),
+            _ => None,
+        }
+    }
+
+    // True if a game won at the given moment belongs to this period
+    fn contains(self, time: SystemTime, now: SystemTime) -> bool {
+        let days = day_number(now).saturating_sub(day_number(time));
+        match self {
+            Period::Day => days == 0,
+            Period::Week => days < 7,
+            Period::All => true,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Period::Day => "today",
+            Period::Week => "this week",
+            Period::All => "all time",
+        }
+    }
+}
+
+/// All the entries of the leaderboard, the ones that have passed the check
+#[derive(Default)]
+pub struct Leaderboard {
+    /// The entries in the order they were written
+    pub entries: Vec<Entry>,
+    /// How many lines of the file couldn't be read
+    pub skipped: usize,
+    /// How many entries have failed the check (see Entry::verify())
+    pub rejected: usize,
+    /// The spoiled seeds, each with the number of entries read before it. A spoiler only counts
+    /// against the entries that come after it.
+    pub spoilers: Vec<(usize, Spoiler)>,
+}
+
+impl Leaderboard {
+
+    /// Reads the entries from anything that can be read line by line and checks every one of them
+    pub fn load<R: BufRead>(input: R) -> io::Result<Leaderboard> {
+        let mut board = Leaderboard::default();
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+
+            if i == 0 {
+                check_header(&line, MAGIC, LEADERBOARD_VERSION..=LEADERBOARD_VERSION)
+                    .map_err(|error| format!("bad leaderboard file: {}", error))
+                    .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))?;
+                continue;
+            }
+
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with("spoiled ") {
+                match Spoiler::parse(&line) {
+                    Some(spoiler) => board.spoilers.push((board.entries.len(), spoiler)),
+                    None => board.skipped += 1,
+                }
+                continue;
+            }
+            match Entry::parse(&line) {
+                Some(ref entry) if entry.verify() => board.entries.push(entry.clone()),
+                Some(_) => board.rejected += 1,
+                None => board.skipped += 1,
+            }
+        }
+        Ok(board)
+    }
+
+    /// Reads the entries from a file. If there's no file yet, the leaderboard is empty.
+    pub fn load_file(path: &Path) -> io::Result<Leaderboard> {
+        match File::open(path) {
+            Ok(file) => Leaderboard::load(BufReader::new(file)),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(Leaderboard::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Adds an entry to the end of a file, creating the file with its header if needed
+    pub fn append_file(path: &Path, entry: &Entry) -> io::Result<()> {
+        entry.write(&mut open_append(path)?)
+    }
+
+    /// Adds a spoiler to the end of a file, creating the file with its header if needed
+    pub fn append_spoiler(path: &Path, spoiler: &Spoiler) -> io::Result<()> {
+        spoiler.write(&mut open_append(path)?)
+    }
+
+    /// The entries of a period with the given rules and, if it's given, the given seed, from the
+    /// best to the worst. Entries with the same tries are ranked by time.
+    pub fn ranking(&self, config: &GameConfig, seed: Option<u64>, period: Period) -> Vec<&Entry> {
+        let now = SystemTime::now();
+
+        // The entries are in the order they were won, so the first one of a player on a seed is
+        // the one we meet first. The seen list remembers who has already got an entry on what.
+        let mut seen = Vec::new();
+        let mut ranking = Vec::new();
+        let entries = self.entries.iter().enumerate().filter(|&(_, entry)| entry.config == *config);
+        for (i, entry) in entries {
+            let key = (&entry.player, entry.seed);
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+
+            // A spoiler written before the first win means the player knew the number
+            let spoiled = |&(before, ref spoiler): &(usize, Spoiler)| {
+                before <= i && spoiler.spoils(entry)
+            };
+            if self.spoilers.iter().any(spoiled) {
+                continue;
+            }
+
+            // The period is checked after the first win is found, so a replay this week doesn't
+            // count when the puzzle was first solved last month
+            if seed.is_none_or(|seed| entry.seed == seed) && period.contains(entry.finished, now) {
+                ranking.push(entry);
+            }
+        }
+
+        // The sort is stable, so of two equal entries the earlier one stays first
+        ranking.sort_by_key(|entry| (entry.tries(), entry.duration));
+        ranking
+    }
+
+    /// Writes the ranking for some rules and seed as text for the terminal
+    pub fn write_text<W: Write>(&self, out: &mut W, config: &GameConfig, seed: Option<u64>,
+                                period: Period) -> io::Result<()> {
+        let (rules, when) = (config.describe(), period.describe());
+        match seed {
+            Some(seed) => writeln!(out, "Leaderboard of seed {}, {}, {}", seed, rules, when)?,
+            None => writeln!(out, "Leaderboard of all seeds, {}, {}", rules, when)?,
+        }
+
+        let ranking = self.ranking(config, seed, period);
+        if ranking.is_empty() {
+            writeln!(out, "No games won yet")?;
+        }
+        for (place, entry) in ranking.iter().enumerate() {
+            let name = if entry.player == ANONYMOUS { "anonymous" } else { &entry.player };
+            let seconds = entry.duration.as_secs();
+            write!(out, "{:>3}. {:<20} {:>3} tries {:>4}:{:02}", place + 1, name, entry.tries(),
+                   seconds / 60, seconds % 60)?;
+
+            // Without a seed, every line says which puzzle it was
+            if seed.is_none() {
+                write!(out, "  seed {}", entry.seed)?;
+            }
+            writeln!(out, "{}", if entry.hard { "  hard" } else { "" })?;
+        }
+
+        if self.rejected > 0 {
+            writeln!(out, "{} entries don't add up and were left out", self.rejected)?;
+        }
+        if self.skipped > 0 {
+            writeln!(out, "{} damaged entries were skipped", self.skipped)?;
+        }
+        Ok(())
+    }
+}
+
+// Opens a file to add lines to its end. A new file gets the header first.
+fn open_append(path: &Path) -> io::Result<File> {
+    let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        write_header(&mut file, MAGIC, LEADERBOARD_VERSION)?;
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ANONYMOUS, Entry, Leaderboard, Period, Spoiler};
+    use game::{Game, GameConfig, Guess};
+
+    // The seed 42 hides 5780 among the classic numbers, and these guesses find it
+    const GUESSES: [&str; 5] = ["0123", "1456", "2578", "3785", "5780"];
+
+    // Plays the guesses on the seed 42 and makes an entry of the game
+    fn entry(player: &str, guesses: &[&str]) -> Entry {
+        let mut game = Game::new(GameConfig::default(), Some(42));
+        for guess in guesses {
+            game.try(&Guess::parse(guess, &game.config).unwrap());
+        }
+        Entry::from_game(&game, player).unwrap()
+    }
+
+    // Writes the lines the way the game appends them and reads them back
+    fn board(entries: &[Entry], spoilers: &[(usize, Spoiler)]) -> Leaderboard {
+        let mut file = b"cowbull-leaderboard 1\n".to_vec();
+        for i in 0..=entries.len() {
+            for (_, spoiler) in spoilers.iter().filter(|&&(before, _)| before == i) {
+                spoiler.write(&mut file).unwrap();
+            }
+            if let Some(entry) = entries.get(i) {
+                entry.write(&mut file).unwrap();
+            }
+        }
+        let board = Leaderboard::load(&file[..]).unwrap();
+        assert_eq!(board.spoilers.len(), spoilers.len());
+        board
+    }
+
+    fn players(board: &Leaderboard) -> Vec<&str> {
+        board.ranking(&GameConfig::default(), Some(42), Period::All)
+            .iter()
+            .map(|entry| entry.player.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn honest_entry_passes() {
+        assert!(entry("alice", &GUESSES).verify());
+        assert!(entry("bob", &["5780"]).verify());
+    }
+
+    #[test]
+    fn unfinished_game_is_not_an_entry() {
+        let mut game = Game::new(GameConfig::default(), Some(42));
+        game.try(&Guess::parse("0123", &game.config).unwrap());
+        assert!(Entry::from_game(&game, "alice").is_none());
+        assert!(Entry::from_game(&Game::new(GameConfig::default(), None), "alice").is_none());
+    }
+
+    #[test]
+    fn tampered_entry_is_rejected() {
+        let honest = entry("alice", &GUESSES);
+
+        // Fewer tries than it took: the last guess doesn't win anymore
+        let mut shorter = honest.clone();
+        shorter.guesses.pop();
+        assert!(!shorter.verify());
+
+        // The winning guess in the middle: the game would have ended there
+        let mut early = honest.clone();
+        early.guesses[1] = early.guesses[4].clone();
+        assert!(!early.verify());
+
+        // Another seed has another number
+        let mut seed = honest.clone();
+        seed.seed = 43;
+        assert!(!seed.verify());
+
+        // A guess that breaks the rules
+        let mut repeated = honest.clone();
+        repeated.guesses[0] = vec![1, 1, 2, 3];
+        assert!(!repeated.verify());
+
+        // Hard mode checks every guess against the answers before it: 0123 has one cow, so a
+        // second guess without any of its symbols contradicts it
+        let mut hard = entry("alice", &["0123", "4567", "5780"]);
+        assert!(hard.verify());
+        hard.hard = true;
+        assert!(!hard.verify());
+
+        // Nothing at all
+        let mut empty = honest;
+        empty.guesses.clear();
+        assert!(!empty.verify());
+    }
+
+    #[test]
+    fn tampered_line_is_counted() {
+        let mut file = b"cowbull-leaderboard 1\n".to_vec();
+        entry("alice", &GUESSES).write(&mut file).unwrap();
+        let text = String::from_utf8(file).unwrap()
+            .replace("01231456257837855780", "0123145657803785");
+        let board = Leaderboard::load(text.as_bytes()).unwrap();
+        assert_eq!((board.entries.len(), board.rejected), (0, 1));
+    }
+
+    #[test]
+    fn fewer_tries_rank_higher() {
+        let board = board(&[entry("alice", &GUESSES), entry("bob", &["5780"])], &[]);
+        assert_eq!(players(&board), ["bob", "alice"]);
+    }
+
+    #[test]
+    fn only_the_first_win_counts() {
+        let entries = [entry("alice", &GUESSES), entry("alice", &["5780"]),
+                       entry("bob", &["0123", "5780"])];
+        let board = board(&entries, &[]);
+        assert_eq!(players(&board), ["bob", "alice"]);
+        assert_eq!(board.ranking(&GameConfig::default(), Some(42), Period::All)[1].tries(), 5);
+    }
+
+    #[test]
+    fn spoiled_seed_is_not_ranked() {
+        let game = Game::new(GameConfig::default(), Some(42));
+        let spoiler = |player: &str| Spoiler::from_game(&game, player).unwrap();
+
+        // Carol gave the game up before winning it, Bob did after his win
+        let entries = [entry("carol", &["5780"]), entry("bob", &GUESSES)];
+        let board = board(&entries, &[(0, spoiler("carol")), (2, spoiler("bob"))]);
+        assert_eq!(players(&board), ["bob"]);
+    }
+
+    #[test]
+    fn players_without_a_profile_are_ranked_as_one() {
+        // They all look the same to us, so only the first of their wins is ranked
+        let entries = [entry(ANONYMOUS, &GUESSES), entry("alice", &["0123", "5780"]),
+                       entry(ANONYMOUS, &["5780"])];
+        let board = board(&entries, &[]);
+        assert_eq!(players(&board), ["alice", ANONYMOUS]);
+    }
+}
@@ -0,0 +1,46 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! The engine of the Cows and Bulls game. It keeps the secret number, scores guesses, figures out
+//! hints on symbol positions and can play the game by itself. The cowbull program is just one
+//! frontend for it, bots and services can link this library and use the same engine.
+//!
+//! A game is made from its rules and an optional seed. Guesses are parsed according to the rules:
+//!
+//! ```
+//! use cowbull::game::{Game, GameConfig, Guess};
+//!
+//! let mut game = Game::new(GameConfig::default(), Some(42));
+//! let guess = Guess::parse("0123", &game.config).unwrap();
+//!
+//! let (cows, bulls, won) = game.play(&guess);
+//! if !won {
+//!     assert!(cows + bulls <= 4);
+//!     game.analyze();
+//! }
+//! ```
+//!
+//! Scoring doesn't need a game at all, see `game::score()`. The `solver` module plays the game for
+//! you with a strategy of your choice, the `tournament` module tells which strategy plays best,
+//! `analysis` compares a played game with the solver, `stats` keeps the results of every game,
+//! `leaderboard` ranks the players of seeded games, `profile` keeps the preferences of every
+//! player, `transcript` writes down whole games and plays them back, and `storage` knows where to
+//! keep files between runs.
+
+// Every public item of the library must be documented, the compiler will remind us otherwise.
+// Attributes that start with #! apply to the whole crate.
+#![warn(missing_docs)]
+
+// This is the root of our library crate. Everything the library offers is in these modules, and
+// they are public, so the programs that link the library can use them.
+pub mod analysis;
+pub mod game;
+pub mod leaderboard;
+pub mod profile;
+pub mod solver;
+pub mod stats;
+pub mod storage;
+pub mod tournament;
+pub mod transcript;
@@ -5,29 +5,253 @@
 
 // Import STDIN and STDOUT handle generators for input and output respectively.
 // We need the std::io::Write trait so we can flush the output buffer later.
-use std::io::{stdin, stdout};
+use std::io::{stdin, stdout, BufReader};
 use std::io::Write;
 
-// Declare our game module and import it's definitions in this module's scope
-pub mod game;
-use game::*;
+// We'll need command line arguments to choose the rules and a way to quit with an error code
+use std::env;
+use std::process;
+
+// Files and their paths for saving and loading games
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The daily puzzle needs to know what day it is
+use std::time::{Duration, SystemTime};
+
+// The game engine lives in our library crate (see lib.rs), so other programs can use it too.
+// This file is only a frontend that talks to the player. We link the library and import the
+// definitions of its game module in this module's scope.
+extern crate cowbull;
+use cowbull::game::*;
+
+// The computer player and the ways it can choose its guesses
+use cowbull::solver::{self, Builtin, Solver, Strategy};
+
+// Strategies can compete with each other in a tournament
+use cowbull::tournament::{self, Report};
+
+// The place where we keep files between runs, like the auto-saved game
+use cowbull::storage;
+
+// A played game can be compared with the solver, guess by guess
+use cowbull::analysis;
+
+// Every finished game is recorded, so the player can see how they do over time
+use cowbull::stats::Stats;
+
+// Seeded games are ranked on a leaderboard
+use cowbull::leaderboard::{Leaderboard, Period};
+
+// Players who share a computer have their own profiles
+use cowbull::profile::{self, Profile};
+
+// Whole games can be written down and played back
+use cowbull::stats::Outcome;
+use cowbull::transcript::Transcript;
+
+// The reverse game, where the computer guesses our number. It talks to the player, so it belongs
+// to the frontend and is not a part of the library.
+mod reverse;
+
+// The batch mode plays a game with guesses from a file or a pipe, for scripts
+mod batch;
+
+// The JSON lines protocol lets bots play the game
+mod protocol;
+
+// Two humans can play against each other
+mod duel;
+
+// Many players can race to guess the same number on the server
+mod race;
+
+// The game can also be played on the full screen of the terminal
+mod tui;
+
+// The server hosts many sessions over the network
+mod server;
+
+// The session keeps the current game and counts the games played since the program has started
+mod session;
+use session::Session;
+
+// Everything we've learned from the command line arguments
+struct Options {
+    // The rules of the game
+    config: GameConfig,
+    // When true, the computer guesses and the human keeps the secret
+    reverse: bool,
+    // The strategy the computer uses to guess in the reverse game, or the only one to play in the
+    // tournament. Box<dyn Strategy> can hold any type that implements the Strategy trait.
+    strategy: Option<Box<dyn Strategy>>,
+    // When true, strategies play against every secret number instead of a game with the human
+    tournament: bool,
+    // The seed of the secret number, if the game should be reproducible
+    seed: Option<u64>,
+    // The moment the daily puzzle was picked, if this is the daily puzzle. The seed and the date we
+    // show are both made from it, so they can't disagree even if the program starts at midnight.
+    daily: Option<SystemTime>,
+    // When true, every guess must agree with the earlier answers
+    hard: bool,
+    // When true, the evil host doesn't pick the secret number until it has to
+    evil: bool,
+    // When true, guesses are read without prompts and answered in a machine-readable way
+    batch: bool,
+    // The file to read the guesses from in the batch mode, or None for the standard input
+    input: Option<PathBuf>,
+    // When true, a bot plays using the JSON lines protocol
+    json: bool,
+    // When true, two humans play against each other
+    duel: bool,
+    // When true, the players of the server race to guess the same number
+    race: bool,
+    // When true, the game takes the whole screen of the terminal
+    tui: bool,
+    // When true, we host games over the network on this address and port, for up to so many
+    // sessions at once, closing the ones that are idle for so many seconds
+    serve: bool,
+    host: String,
+    port: u16,
+    max_sessions: usize,
+    idle: u64,
+    // The name of the player's profile, if the player has picked one
+    player: Option<String>,
+    // When true, the rules were given on the command line, so the ones of the profile are not used
+    rules: bool,
+    // When true, the analysis report is shown after every win
+    report: bool,
+}
 
 fn main() {
-    // Create a game object. Note that it's mutable so we can change it's internal variables. It's
-    // also worth to mention that mutability is spread on all struct variables and there's no way
-    // to make some of them mutable and others not.
-    let mut the_game = Game::new();
+    // First, we figure out the rules of the game from the command line. If the arguments don't
+    // make sense, we tell the user what's wrong and quit with a non-zero exit code, which is
+    // a common way to tell the shell that something went wrong.
+    let mut options = match parse_args() {
+        Ok(options) => options,
+        Err(message) => {
+            println!("{}", message);
+            process::exit(2);
+        },
+    };
+
+    // The profile of the player fills in what the command line doesn't say
+    if let Some(name) = options.player.clone() {
+        match open_profile(&name) {
+            Ok(profile) => apply_profile(&mut options, &profile),
+            Err(message) => {
+                println!("{}", message);
+                process::exit(2);
+            },
+        }
+    }
+
+    // In the reverse game the roles are swapped, so it's handled by another module. Without
+    // a strategy given, Knuth's minimax plays. The as_deref() lets us borrow the strategy from
+    // the box inside of the Option.
+    if options.reverse {
+        reverse::play(&options.config, options.strategy.as_deref().unwrap_or(&Builtin::Minimax));
+        return;
+    }
+
+    // The tournament doesn't need a player at all
+    if options.tournament {
+        run_tournament(&options);
+        return;
+    }
+
+    // A duel at one terminal doesn't need a session, the duel keeps the games of both players
+    if options.duel && !options.serve {
+        let _ = duel::play(&mut duel::HotSeat, &options.config, options.hard);
+        return;
+    }
 
-    // Just print an invitation line using a println! macros
-    println!("Guess the number! (Enter 'q' to quit', 'h' for help)");
+    // Create a session with its first game. Note that it's mutable so we can change it's internal
+    // variables. It's also worth to mention that mutability is spread on all struct variables and
+    // there's no way to make some of them mutable and others not.
+    let mut session = match Session::new(options.config.clone(), options.seed, options.hard, options.evil) {
+        Ok(session) => session,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(2);
+        },
+    };
+
+    // The batch mode plays just one game and tells the result with the exit code
+    if options.batch {
+        let code = match options.input {
+            Some(ref path) => match fs::File::open(path) {
+                Ok(file) => batch::play(&mut session.game, BufReader::new(file)),
+                Err(error) => {
+                    eprintln!("Can't open {}: {}", path.display(), error);
+                    batch::EXIT_INVALID
+                },
+            },
+            None => batch::play(&mut session.game, stdin().lock()),
+        };
+        process::exit(code);
+    }
+
+    // The server makes a session for every connection. The one we've just made has checked the
+    // rules, and it's not needed anymore.
+    if options.serve {
+        let settings = server::Settings {
+            config: options.config.clone(),
+            seed: options.seed,
+            hard: options.hard,
+            evil: options.evil,
+            json: options.json,
+            duel: options.duel,
+            race: options.race,
+            max_sessions: options.max_sessions,
+            idle: Duration::from_secs(options.idle),
+        };
+        if let Err(error) = server::run(&format!("{}:{}", options.host, options.port), settings) {
+            println!("Can't run the server: {}", error);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // A bot gets nothing but JSON, so there's no intro and no auto-save
+    if options.json {
+        let stdout = stdout();
+        if let Err(error) = protocol::serve(&mut session, stdin().lock(), &mut stdout.lock()) {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+        return;
+    }
+
+    // From here on the games are played by the human at the terminal, so we record them. If the
+    // stats file has no place to live, the games are played all the same.
+    play_as(&mut session, options.player.clone());
+
+    // The full-screen mode has its own loop
+    if options.tui {
+        if let Err(error) = tui::play(&mut session) {
+            println!("Can't start the full-screen mode: {}", error);
+            process::exit(1);
+        }
+        session.print_summary();
+        return;
+    }
+
+    print_intro(&session.game, &options);
+    remind_autosave(&session);
 
     loop {
+        // The session records every finished game, and it tells us if that has failed
+        if let Some(error) = session.record_error() {
+            println!("Can't record the game: {}", error);
+        }
+
         // We'll need an empty String object to feed it to IO object as buffer
         let mut input = String::new();
 
         // Print prompt decoration. We want an imput invitation that displays how many tries have
         // been used so far to guess the secret number
-        print!("{} > ", the_game.tries);
+        print!("{} > ", session.game.tries);
 
         // Flush buffer so the decoration will be printed immedeately without line buffering.
         // Note, that this will fail if you don't include std::io::Write trait. Also, we assign
@@ -36,138 +260,964 @@ fn main() {
         let _ = stdout().flush();
 
         // Read from STDIN to our buffer variable. read_line() method returns a Result object so
-        // we need to handle it's success and fail status.
-        stdin().read_line(&mut input)
-            .ok()
-            .expect("Failed to read input");
+        // we need to handle it's success and fail status. Zero bytes read means the input is over,
+        // for example the player has pressed Ctrl+D, and then we act as if 'q' was entered.
+        match stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => {
+                println!();
+                input = "q".to_string();
+            },
+            Ok(_) => (),
+        }
 
         // Trim string. Note that trim() method will return &str type, not String. But we don't
         // need the String type any further because we don't want this value to be borrowed.
         // We use "let" to redefine this binding with new type.
         let input = input.trim();
 
-        // Now we attempt to parse the input string to a u32 integer. On success, we have a number
-        // and will check if matches our secret number. On fail, we assume that user have entered
-        // a command and we will try to figure out which one exactly.
-        match input.parse::<u32>() {
-
-            // Our first case. Since we must have four digits according to our game rules, we'll
-            // check if input string's length is four. If not, we just print the warning and
-            // proceed with the game loop.
-            Ok(_) if input.len() != 4 => println!("Number of four digits is needed"),
-
-            // Next, according to our game's rules, all digits of the numbers should be different.
-            // So we call a game method that will check this for us. check_unique_digits() returns
-            // a bool typed value.
-            Ok(_) if !the_game.check_unique_digits(input) => println!("Digits must be unique"),
-
-            // If previous checks have filtered us a valid number, we'll ask our game object to
-            // check this number against the secret one. If it matches, we will get the true bool
-            // value, print the win message with a number of tries needed to guess the right answer
-            // and break the loop so game will end.
-            Ok(_) if the_game.guess(input) => {
-                println!("You won in {} tries!", the_game.tries + 1);
+        // First, we check if the user have entered a command. We can't tell commands from numbers
+        // by parsing the input as an integer anymore, because with letter or hexadecimal rules
+        // the secret code is not a number. So we try known commands first and treat everything
+        // else as a guess.
+        match input {
+
+            // If we encounter an empty value, we just restart the game loop waiting for
+            // non-empty command.
+            "" => continue,
+
+            // Here we define a pattern that matches one of these strings for a command that
+            // quits the game. The break operator will end the game loop and after it
+            // we only print the summary of the session before returning user to shell.
+            // Before we go, we save the game, so it can be resumed next time. There's no point in
+            // saving a game without a single guess. A saved game isn't over, so it's not recorded
+            // until it's won, restarted or left for another one (see try_autosave()).
+            "q" | "quit" | "exit" => {
+                suspend(&mut session);
                 break;
             },
 
-            // If the input number was correct, but didn't match the secret value, we'll analyze it
-            // and print the number of "cows" (digit exists, but doesn't match position) and
-            // "bulls" (digit exists in secret number and matches position).
-            Ok(_) => {
-                // Here we ask the game object for tuple containing cows and bulls. We use
-                // automatic type allocation and our code expects simple integers, so we don't
-                // really need to think about particular type of these bindings at the moment.
-                let (cows, bulls) = the_game.try(input);
-
-                // We ask the game object to analyze acquired result and save hints on digit
-                // positions that are being entered
-                the_game.analyze(input, cows, bulls);
-
-                // Now we print number of found matches if there was any
-                if cows == 0 && bulls == 0 {
-                    println!("Nothing found");
-                } else {
-                    println!("Found {} cows and {} bulls", cows, bulls);
+            // We'll want to show user a list of available commands, so we call the
+            // print_help() function which will handle this for us.
+            "h" | "help" | "?" => print_help(&session.game.config),
+
+            // This command calls print_hint(). Read about it below.
+            "s" | "hints" => print_hint(&session.game),
+
+            // This one tells how the games of this session went, or with --global, how all the
+            // recorded games went
+            "stats" => if session.played() > 0 {
+                session.print_summary();
+            } else {
+                println!("No games played yet, enter 'stats --global' to see the earlier ones");
+            },
+            _ if input.split_whitespace().next() == Some("stats") => match input.split_whitespace().nth(1) {
+                Some("--global") => print_stats(session.player()),
+                _ => println!("Enter 'stats' or 'stats --global'"),
+            },
+
+            // This one shows every guess made so far next to the same hints
+            "history" => print_history(&session.game),
+
+            // This command gives up the current game and starts a new one. The session replaces
+            // the game object, and the old one is dropped, so nothing piles up no matter how many
+            // times we restart. If the game has any guesses, it's the auto-saved one, and there's
+            // nothing to resume anymore.
+            "r" | "restart" => {
+                if session.game.tries > 0 {
+                    forget_autosave(&session);
                 }
-            }
+                session.restart();
+                print_intro(&session.game, &options);
+            },
+
+            // These commands save the game to a file and load it back. The file name is the second
+            // word of the input. Loading without a name picks up the auto-saved game.
+            _ if input.split_whitespace().next() == Some("save") => match input.split_whitespace().nth(1) {
+                Some(name) => match session.game.save_file(Path::new(name)) {
+                    Ok(()) => println!("Game saved to {}", name),
+                    Err(error) => println!("Can't save the game: {}", error),
+                },
+                None => println!("Enter a file name: save <file>"),
+            },
+            _ if input.split_whitespace().next() == Some("load") => {
+                let path = match input.split_whitespace().nth(1) {
+                    Some(name) => PathBuf::from(name),
+                    None => match storage::autosave_path(session.player()) {
+                        Ok(path) => path,
+                        Err(error) => {
+                            println!("Can't find the saved game: {}", error);
+                            continue;
+                        },
+                    },
+                };
+                match Game::load_file(&path) {
+                    // The loaded game replaces the current one completely
+                    Ok(game) => {
+                        session.game = game;
+                        println!("Game loaded from {}, {} tries so far", path.display(), session.game.tries);
+                    },
+                    Err(error) => println!("Can't load the game: {}", error),
+                }
+            },
+
+            // This command shows how well the guesses were chosen. The current game is reported if
+            // it has any guesses, otherwise the last won one. With a file name, the report is
+            // written to the file as Markdown.
+            _ if input.split_whitespace().next() == Some("report") => {
+                let game = if session.game.tries > 0 { Some(&session.game) } else { session.last_game() };
+                match game {
+                    Some(game) => report(game, input.split_whitespace().nth(1).map(Path::new)),
+                    None => println!("No guesses yet"),
+                }
+            },
+
+            // These commands write the transcript of a game to a file and play one back. Like the
+            // report, the export is of the current game if it has guesses, otherwise of the last
+            // won one. The transcript tells the secret number, so exporting a game in progress is
+            // as good as giving up: a seeded game can't get on the leaderboard after that.
+            _ if input.split_whitespace().next() == Some("export") => match input.split_whitespace().nth(1) {
+                Some(name) => {
+                    let current = session.game.tries > 0;
+                    let transcript = if current {
+                        Some(Transcript::from_game(&session.game, None))
+                    } else {
+                        session.last_game().map(|game| Transcript::from_game(game, Some(Outcome::Won)))
+                    };
+                    match transcript {
+                        Some(transcript) => match transcript.save_file(Path::new(name)) {
+                            Ok(()) => {
+                                println!("Transcript written to {}", name);
+                                if current {
+                                    session.spoil();
+                                }
+                            },
+                            Err(error) => println!("Can't write the transcript: {}", error),
+                        },
+                        None => println!("No guesses yet"),
+                    }
+                },
+                None => println!("Enter a file name: export <file>"),
+            },
+            _ if input.split_whitespace().next() == Some("replay") => match input.split_whitespace().nth(1) {
+                Some(name) => replay(Path::new(name)),
+                None => println!("Enter a file name: replay <file>"),
+            },
+
+            // This command ranks the players of the current puzzle. The period is the second word.
+            _ if input.split_whitespace().next() == Some("leaderboard") => {
+                match input.split_whitespace().nth(1).map(Period::from_name).unwrap_or(Some(Period::All)) {
+                    Some(period) => print_leaderboard(&session.game, period),
+                    None => println!("Enter leaderboard [day|week|all]"),
+                }
+            },
+
+            // This command shows the profile of the player, switches to another one or changes it
+            _ if input.split_whitespace().next() == Some("profile") => profile_command(&mut session, &mut options, input),
+
+            // This command lets the computer finish the game for us. It can be followed by the name
+            // of a strategy, so we check only the first word of the input. If the computer has
+            // won, the game is over.
+            _ if input.split_whitespace().next() == Some("solve") => {
+                if solve(&mut session.game, input) {
+                    // The computer has won this one, so it's not our win. We just move on.
+                    forget_autosave(&session);
+                    session.solved();
+                    print_intro(&session.game, &options);
+                }
+            },
+
+            // If the input has no symbols of our alphabet at all, it's neither a command nor a
+            // guess. So we just print a message that we didn't get what user wanted and hint him
+            // to use help command.
+            _ if !input.chars().any(|c| session.game.config.index_of(c).is_some()) =>
+                println!("Unknown command: \"{}\". Enter 'h' for help", input),
+
+            // Everything else must be a guess. We ask the Guess type to parse the input according
+            // to the rules of our game and start another match operator on the result.
+            _ => match Guess::parse(input, &session.game.config) {
+
+                // If the guess is wrong in some way (too short, has a letter or a repeated digit),
+                // the error tells exactly what's wrong. GuessError implements the Display trait,
+                // so we can just print it and proceed with the game loop.
+                Err(error) => println!("{}", error),
+
+                // If previous check has given us a valid number, we'll ask our game object to
+                // play it. The game checks this number against the secret one, scores it and
+                // tells if it was the right answer, so the winning guess gets into the history of
+                // the game and the report below can tell how it was chosen.
+                Ok(guess) => {
+                    // In hard mode a guess that contradicts an earlier answer doesn't count. We
+                    // explain what's wrong with it and wait for another one.
+                    if let Err(contradiction) = session.game.check_hard(&guess) {
+                        println!("{}", contradiction);
+                        continue;
+                    }
+
+                    // Here we ask the game object for tuple containing cows, bulls and whether
+                    // we've won. We use automatic type allocation and our code expects simple
+                    // values, so we don't really need to think about particular type of these
+                    // bindings at the moment.
+                    let (cows, bulls, won) = session.game.play(&guess);
+
+                    // If it matches, we print the win message with a number of tries needed to
+                    // guess the right answer and let the session start the next game
+                    if won {
+                        let tries = session.game.tries;
+                        println!("You won in {} tries!", tries);
+                        forget_autosave(&session);
+                        if options.report {
+                            report(&session.game, None);
+                        }
+                        session.win();
+                        print_intro(&session.game, &options);
+                        continue;
+                    }
+
+                    // Otherwise we analyze it and print the number of "cows" (digit exists, but
+                    // doesn't match position) and "bulls" (digit exists in secret number and
+                    // matches position). We ask the game object to analyze acquired result and
+                    // update hints on digit positions. The game remembers every answer, so
+                    // there's nothing to pass in.
+                    session.game.analyze();
+
+                    // Now we print number of found matches if there was any
+                    if cows == 0 && bulls == 0 {
+                        println!("Nothing found");
+                    } else {
+                        println!("Found {} cows and {} bulls", cows, bulls);
+                    }
+
+                    // Save the game after every guess, so it's not lost even if the terminal is
+                    // closed without quitting
+                    autosave(&mut session);
+                },
+            },
+        };
+    }
+
+    // The loop is over, so the session is too
+    session.print_summary();
+}
+
+// Prints an invitation line for a new game using a println! macros
+fn print_intro(game: &Game, options: &Options) {
+    println!("Guess the number of {}! (Enter 'q' to quit', 'h' for help)", game.config.describe());
+    if game.hard {
+        println!("Hard mode: every guess must agree with all earlier answers");
+    }
+    if game.is_evil() {
+        println!("Evil mode: I won't pick my number until you leave me no choice");
+    }
+
+    // Let the player know which puzzle this is, so it can be compared with others
+    if let Some(today) = options.daily {
+        println!("Daily challenge for {}", daily::format_date(today));
+    } else if let Some(seed) = options.seed {
+        println!("Seed {}", seed);
+    }
+}
+
+// If the last game of the player wasn't finished, remind the player that it can be resumed. The
+// if let construct runs the block only if the pattern matches.
+fn remind_autosave(session: &Session) {
+    if let Ok(path) = storage::autosave_path(session.player()) {
+        if path.exists() {
+            println!("Your last game was saved, enter 'load' to resume it");
+        }
+    }
+}
+
+// Saves the current game to the auto-save file of the player. We only complain if something goes
+// wrong.
+fn autosave(session: &mut Session) {
+    if let Err(error) = try_autosave(session) {
+        println!("Can't auto-save the game: {}", error);
+    }
+}
+
+// The same, but the error is left to the caller. The full-screen mode can't just print it.
+//
+// The file may still hold a game of an earlier run that the player didn't resume. It's about to
+// be lost, so now is the time to record it as given up. It's the same game if it has started at
+// the same moment, which the file keeps in whole seconds, and if every saved guess was made in
+// the current game too.
+fn try_autosave(session: &mut Session) -> Result<(), SaveError> {
+    let path = storage::autosave_path(session.player())?;
+    if let Ok(saved) = Game::load_file(&path) {
+        let history = &session.game.history;
+        let same = unix_time(saved.started) == unix_time(session.game.started)
+            && saved.history.len() <= history.len()
+            && saved.history.iter().zip(history).all(|(a, b)| a.guess == b.guess);
+        if !same {
+            session.abandon(saved);
+        }
+    }
+    session.game.save_file(&path)
+}
+
+// Puts the current game aside when the player leaves, so it can be resumed next time. If it
+// can't be saved, it's over, and we record it as given up.
+fn suspend(session: &mut Session) {
+    if session.game.tries == 0 {
+        return;
+    }
+    match try_autosave(session) {
+        Ok(()) => (),
+        Err(error) => {
+            println!("Can't auto-save the game: {}", error);
+            session.finish();
+        },
+    }
+}
+
+// Removes the auto-saved game when it's over, so nobody tries to resume it. If there's no such
+// file, there's nothing to do, so we ignore the result.
+fn forget_autosave(session: &Session) {
+    if let Ok(path) = storage::autosave_path(session.player()) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// Finds the profile of a player in the profiles file, making a new one if it's not there yet
+fn open_profile(name: &str) -> Result<Profile, String> {
+    if !profile::valid_name(name) {
+        return Err(profile::ProfileError::BadName(name.to_string()).to_string());
+    }
+    storage::profiles_path()
+        .map_err(profile::ProfileError::from)
+        .and_then(|path| profile::open(&path, name))
+        .map_err(|error| format!("Can't open the profile: {}", error))
+}
+
+// Uses the preferences of a profile for everything the command line hasn't set
+fn apply_profile(options: &mut Options, profile: &Profile) {
+    if !options.rules {
+        options.config = profile.config.clone();
+    }
+    options.hard = options.hard || profile.hard;
+    options.report = profile.report;
+
+    // The full screen is only for the usual game against the computer
+    let other_mode = options.reverse || options.tournament || options.duel || options.json || options.batch || options.serve;
+    options.tui = options.tui || (profile.tui && !other_mode);
+}
+
+// Tells the session whose games these are, so they are saved and recorded in the right folder
+fn play_as(session: &mut Session, player: Option<String>) {
+    session.set_player(player);
+    if let Ok(path) = storage::stats_path(session.player()) {
+        session.keep_stats(path);
+    }
+    if let Ok(path) = storage::leaderboard_path() {
+        session.keep_leaderboard(path);
+    }
+    if let Ok(dir) = storage::transcripts_dir(session.player()) {
+        session.keep_transcripts(dir);
+    }
+}
 
-            // So we failed to parse our input string as integer, so it must be a command. We start
-            // another match operator.
-            Err(_) => match input {
-
-                // If we encounter an empty value, we just restart the game loop waiting for
-                // non-empty command.
-                "" => continue,
-
-                // Here we define a pattern that matches one of these strings for a command that
-                // quits the game. The break operator will end the game loop and since there's
-                // nothing after this loop, the application will close returning user to shell.
-                "q" | "quit" | "exit" => break,
-
-                // We'll want to show user a list of available commands, so we call the
-                // print_help() function which will handle this for us.
-                "h" | "help" | "?" => print_help(),
-
-                // This command calls print_hint(). Read about it below.
-                "s" | "stats" => print_hint(the_game.hint_table),
-
-                // This simple command recursively calls the main() function effecrively restarting
-                // the game. Make sure to break the loop, so we don't restart game loop after we
-                // have returned from this call.
-
-                // Note, that this kind of implementation is not really good one, but it'll be fine
-                // for this tutorial. It's drawback is that with every game restart the local stack
-                // increases and allocates a new game object that's data is being piled up in the
-                // heap.
-                "r" | "restart" => {
-                    main();
-                    break;
+// Handles the profile command. Without a name it shows the current profile, with a name it
+// switches to another player, and "profile set <setting> <value>" changes a setting.
+fn profile_command(session: &mut Session, options: &mut Options, input: &str) {
+    let words = input.split_whitespace().collect::<Vec<&str>>();
+    match words.as_slice() {
+        [_] => match options.player {
+            Some(ref name) => match open_profile(name) {
+                Ok(profile) => {
+                    println!("Playing as {}. Settings for the next time:", name);
+                    for line in profile.describe() {
+                        println!("  {}", line);
+                    }
                 },
+                Err(message) => println!("{}", message),
+            },
+            None => println!("Playing without a profile, enter 'profile <name>' to pick one"),
+        },
+        [_, "set", key, value] => {
+            let name = match options.player {
+                Some(ref name) => name.clone(),
+                None => {
+                    println!("Pick a profile first: profile <name>");
+                    return;
+                },
+            };
+            let changed = storage::profiles_path()
+                .map_err(profile::ProfileError::from)
+                .and_then(|path| profile::update(&path, |profiles| profiles.find_or_add(&name)?.change(key, value)));
+            match changed {
+                Ok(()) => println!("Saved, {} will be {} the next time {} plays", key, value, name),
+                Err(error) => println!("Can't change the profile: {}", error),
+            }
+        },
+        [_, "set", ..] => println!("Enter profile set <setting> <value>, settings are {}", profile::SETTINGS),
+        [_, name] => switch_profile(session, options, name),
+        _ => println!("Enter profile, profile <name> or profile set <setting> <value>"),
+    }
+}
+
+// Lets another player sit down at the computer. The game of the current player is saved, so it can
+// be resumed later, and the new player gets a session with their own rules.
+fn switch_profile(session: &mut Session, options: &mut Options, name: &str) {
+    let profile = match open_profile(name) {
+        Ok(profile) => profile,
+        Err(message) => {
+            println!("{}", message);
+            return;
+        },
+    };
+
+    // The new session is made first, so nothing changes if the rules don't work with the evil host
+    let next = match Session::new(profile.config.clone(), options.seed, profile.hard, options.evil) {
+        Ok(next) => next,
+        Err(error) => {
+            println!("Can't play the rules of {}: {}", name, error);
+            return;
+        },
+    };
+
+    suspend(session);
+    session.print_summary();
+
+    *session = next;
+    play_as(session, Some(name.to_string()));
+    options.player = Some(name.to_string());
+    options.config = profile.config.clone();
+    options.hard = profile.hard;
+    options.report = profile.report;
+
+    println!("Hi, {}!", name);
+    print_intro(&session.game, options);
+    remind_autosave(session);
+}
+
+// Plays a transcript back turn by turn. After every turn we show the guess with its answer, when
+// it was made, and the hint table as the player saw it then. Every answer is scored again by the
+// game, and if it doesn't match the recorded one, we say so and go on. The player presses Enter
+// for the next turn or enters q to stop.
+fn replay(path: &Path) {
+    let transcript = match Transcript::load_file(path) {
+        Ok(transcript) => transcript,
+        Err(error) => {
+            println!("Can't read the transcript: {}", error);
+            return;
+        },
+    };
+    let mut replay = match transcript.replay() {
+        Ok(replay) => replay,
+        Err(error) => {
+            println!("Can't replay the transcript: {}", error);
+            return;
+        },
+    };
+
+    print!("Replaying a game of {}", transcript.config.describe());
+    if let Some(seed) = transcript.seed {
+        print!(", seed {}", seed);
+    }
+    if let Some(ref secret) = transcript.secret {
+        print!(", secret {}", transcript.config.format_code(secret));
+    }
+    for &(on, mode) in &[(transcript.hard, "hard mode"), (transcript.evil, "evil host")] {
+        if on {
+            print!(", {}", mode);
+        }
+    }
+    println!(", played on {}", daily::format_date(transcript.started));
+    println!("{} turns. Press Enter for the next turn, or enter q to stop.", transcript.turns.len());
+
+    let mut mismatches = 0;
+    while let Some((turn, check)) = replay.step() {
+        // The time is counted from the start of the game, like on a chess clock
+        let seconds = turn.time.duration_since(transcript.started).unwrap_or_default().as_secs();
+        println!();
+        println!("Turn {}: {} - {} cows, {} bulls at {}:{:02}", replay.game().history.len(),
+                 transcript.config.format_code(&turn.guess), turn.cows, turn.bulls, seconds / 60, seconds % 60);
+        if let Err(mismatch) = check {
+            mismatches += 1;
+            println!("Warning: {}", mismatch);
+        }
+        print_hint(replay.game());
+
+        if replay.is_over() {
+            break;
+        }
+        print!("replay > ");
+        let _ = stdout().flush();
+        let mut input = String::new();
+        match stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => return,
+            Ok(_) if input.trim() == "q" => return,
+            Ok(_) => (),
+        }
+    }
+
+    println!();
+    match transcript.outcome {
+        Some(Outcome::Won) => println!("The game was won in {} tries", transcript.turns.len()),
+        Some(Outcome::GaveUp) => println!("The game was given up after {} tries", transcript.turns.len()),
+        Some(Outcome::Solved) => println!("The game was finished by the computer in {} tries", transcript.turns.len()),
+        None => println!("The game wasn't finished"),
+    }
+    if let Err(message) = replay.check_result() {
+        println!("Warning: {}", message);
+    }
+    match mismatches {
+        0 => println!("Every answer matches the game"),
+        count => println!("{} of {} answers don't match the game", count, transcript.turns.len()),
+    }
+}
+
+// Prints the leaderboard of the seed of the game, or of all seeds with the same rules if the game
+// isn't seeded. Every entry is checked while the file is read, so this can take a moment.
+fn print_leaderboard(game: &Game, period: Period) {
+    let board = storage::leaderboard_path().and_then(|path| Leaderboard::load_file(&path));
+    match board {
+        Ok(board) => {
+            let _ = board.write_text(&mut stdout(), &game.config, game.seed, period);
+        },
+        Err(error) => println!("Can't read the leaderboard: {}", error),
+    }
+}
+
+// Prints the statistics of every recorded game of the player
+fn print_stats(player: Option<&str>) {
+    let stats = storage::stats_path(player).and_then(|path| Stats::load_file(&path));
+    match stats {
+        Ok(stats) => {
+            let _ = stats.write_text(&mut stdout());
+        },
+        Err(error) => println!("Can't read the stats: {}", error),
+    }
+}
+
+// Compares the guesses of a game with the solver's and prints the report, or writes it to a file
+// as Markdown. The solver can't analyze games with too many possible numbers, and then we just say
+// so.
+fn report(game: &Game, file: Option<&Path>) {
+    let report = match analysis::analyze(game) {
+        Ok(report) => report,
+        Err(error) => {
+            println!("Can't analyze the game: {}", error);
+            return;
+        },
+    };
 
-                // In case of any other input, we just print a message that we didn't get what user
-                // wanted and hint him to use help command.
-                _  => println!("Unknown command: \"{}\". Enter 'h' for help", input),
+    // Both writers return a Result. The terminal is not worth complaining about, but the file
+    // could be in a folder that doesn't exist, so the player should know if it wasn't written.
+    match file {
+        None => {
+            let _ = report.write_text(&mut stdout());
+        },
+        Some(path) => {
+            let written = fs::File::create(path).and_then(|mut file| report.write_markdown(&mut file));
+            match written {
+                Ok(()) => println!("Report written to {}", path.display()),
+                Err(error) => println!("Can't write the report: {}", error),
+            }
+        },
+    }
+}
+
+// This function lets the solver play the current game to the end. The solver starts from what
+// the player already knows, so every guess made so far counts. Returns true if the game was won.
+fn solve(game: &mut Game, input: &str) -> bool {
+
+    // The second word of the command is the strategy name. Knuth's minimax is the default one.
+    let strategy = match input.split_whitespace().nth(1) {
+        None => Box::new(Builtin::Minimax),
+        Some(name) => match solver::find_strategy(name) {
+            Some(strategy) => strategy,
+            None => {
+                println!("Unknown strategy: \"{}\". Try {}", name, solver::strategy_names());
+                return false;
+            },
+        },
+    };
+
+    // The solver refuses to work when there are too many codes to keep in memory
+    let mut solver = match Solver::from_game(game, &*strategy) {
+        Ok(solver) => solver,
+        Err(error) => {
+            println!("{}", error);
+            return false;
+        },
+    };
+
+    loop {
+        // Ask the solver for a guess and wrap it into a Guess, so we can play it the same way
+        // the main loop does. If the solver has nothing to offer, the answers contradict each
+        // other, which should never happen when the game does the scoring.
+        let variant = match solver.next_guess().and_then(|code| Guess::from_code(code, &game.config)) {
+            Some(variant) => variant,
+            None => {
+                println!("No code is consistent with the answers");
+                return false;
             },
         };
+        println!("{} > {}", game.tries, game.config.format_code(variant.code()));
+        let (cows, bulls, won) = game.play(&variant);
+        if won {
+            println!("Solved with {} strategy in {} tries!", strategy.name(), game.tries);
+            return true;
+        }
+        game.analyze();
+
+        // Let the solver know the answer, so it can drop the candidates that don't fit
+        solver.record(variant.code(), cows, bulls);
+        println!("Found {} cows and {} bulls", cows, bulls);
+    }
+}
+
+// This function lets strategies compete: every strategy plays against every secret number the rules
+// allow, and we print a table of the results. With --strategy only that strategy plays.
+fn run_tournament(options: &Options) {
+    let registered = solver::strategies();
+    let strategies: Vec<&dyn Strategy> = match options.strategy {
+        Some(ref strategy) => vec![&**strategy],
+        None => registered.iter().map(|strategy| &**strategy).collect(),
+    };
+
+    println!("Tournament on {} secret numbers of {}", options.config.space_size(), options.config.describe());
+
+    // Play every strategy and tell how it's going, because smart strategies can take a while
+    let mut reports = Vec::new();
+    for strategy in strategies {
+        println!("Playing {}...", strategy.name());
+        match tournament::run(&options.config, strategy) {
+            Ok(report) => reports.push(report),
+            Err(error) => {
+                println!("{}", error);
+                return;
+            },
+        }
+    }
+
+    print_reports(&reports);
+}
+
+// Prints the results of the tournament as a table: one line per strategy with the average and the
+// worst number of tries, failed games, time per move, and how many games took every number of tries
+fn print_reports(reports: &[Report]) {
+    let worst = reports.iter().map(|report| report.worst()).max().unwrap_or(0);
+
+    print!("{:<14} {:>6} {:>6} {:>6} {:>10}", "strategy", "mean", "worst", "failed", "ms/move");
+    for tries in 1..=worst {
+        print!(" {:>5}", tries);
+    }
+    println!();
+
+    for report in reports {
+        // Durations don't print as milliseconds with fractions, so we compute them ourselves
+        let millis = report.time_per_move().as_secs() as f64 * 1000.0
+            + f64::from(report.time_per_move().subsec_nanos()) / 1_000_000.0;
+        print!("{:<14} {:>6.3} {:>6} {:>6} {:>10.3}", report.strategy, report.mean(), report.worst(), report.failed, millis);
+        for count in &report.tries[1..=worst] {
+            print!(" {:>5}", count);
+        }
+        println!();
+    }
+}
+
+// This function reads the command line arguments and builds the rules of the game out of them.
+// It returns a Result, so we can report a helpful message if something is wrong. Without any
+// arguments we get the classic four decimal digits.
+fn parse_args() -> Result<Options, String> {
+    let mut length = 4;
+    let mut alphabet = Alphabet::Digits;
+    let mut repeats = false;
+    let mut reverse = false;
+    let mut strategy = None;
+    let mut tournament = false;
+    let mut seed = None;
+    let mut daily = false;
+    let mut hard = false;
+    let mut evil = false;
+    let mut batch = false;
+    let mut input = None;
+    let mut json = false;
+    let mut duel = false;
+    let mut race = false;
+    let mut tui = false;
+    let mut serve = false;
+    let mut host = "127.0.0.1".to_string();
+    let mut port = 7878;
+    let mut max_sessions = 16;
+    let mut idle = 300;
+    let mut player = None;
+    let mut rules = false;
+
+    // The first argument is the name of our program, so we skip it
+    let mut args = env::args().skip(1);
+
+    // Most options take a value, so we fetch the next argument along with the option name. The
+    // while let loop runs as long as the iterator returns Some value.
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "-l" | "--length" => {
+                length = match args.next().and_then(|value| value.parse::<usize>().ok()) {
+                    Some(value) => value,
+                    None => return Err(format!("{} needs a number", arg)),
+                };
+                rules = true;
+            },
+            "-a" | "--alphabet" => {
+                alphabet = match args.next() {
+                    Some(value) => Alphabet::from_name(&value),
+                    None => return Err(format!("{} needs digits, hex, letters or a list of symbols", arg)),
+                };
+                rules = true;
+            },
+            "--repeats" => {
+                repeats = true;
+                rules = true;
+            },
+            // The name is checked here, so a bad one is reported like any other bad argument
+            "-p" | "--player" => {
+                player = match args.next() {
+                    Some(ref value) if profile::valid_name(value) => Some(value.clone()),
+                    Some(value) => return Err(profile::ProfileError::BadName(value).to_string()),
+                    None => return Err(format!("{} needs a name", arg)),
+                };
+            },
+            "-r" | "--reverse" => reverse = true,
+            "-s" | "--strategy" => {
+                strategy = match args.next().as_ref().and_then(|value| solver::find_strategy(value)) {
+                    Some(value) => Some(value),
+                    None => return Err(format!("{} needs {}", arg, solver::strategy_names())),
+                };
+            },
+            "-t" | "--tournament" => tournament = true,
+            "--seed" => {
+                seed = match args.next().and_then(|value| value.parse::<u64>().ok()) {
+                    Some(value) => Some(value),
+                    None => return Err(format!("{} needs a number from 0 to {}", arg, u64::MAX)),
+                };
+            },
+            "-d" | "--daily" => daily = true,
+            "--hard" => hard = true,
+            "--evil" => evil = true,
+            "-b" | "--batch" => batch = true,
+            "-j" | "--json" => json = true,
+            "--duel" => duel = true,
+            "--tui" => tui = true,
+            // A race needs many players, so it's only played on the server
+            "--race" => {
+                race = true;
+                serve = true;
+            },
+            // The network options only make sense for the server, so any of them turns it on
+            "--serve" => serve = true,
+            "--host" => {
+                host = match args.next() {
+                    Some(value) => value,
+                    None => return Err(format!("{} needs an address to listen on", arg)),
+                };
+                serve = true;
+            },
+            "--port" => {
+                port = match args.next().and_then(|value| value.parse::<u16>().ok()) {
+                    Some(value) => value,
+                    None => return Err(format!("{} needs a number from 0 to {}", arg, u16::MAX)),
+                };
+                serve = true;
+            },
+            "--max-sessions" => {
+                max_sessions = match args.next().and_then(|value| value.parse::<usize>().ok()) {
+                    Some(value) if value > 0 => value,
+                    _ => return Err(format!("{} needs a number above zero", arg)),
+                };
+                serve = true;
+            },
+            "--idle" => {
+                idle = match args.next().and_then(|value| value.parse::<u64>().ok()) {
+                    Some(value) if value > 0 => value,
+                    _ => return Err(format!("{} needs a number of seconds above zero", arg)),
+                };
+                serve = true;
+            },
+            // Reading guesses from a file only makes sense in the batch mode, so it turns it on
+            "-i" | "--input" => {
+                input = match args.next() {
+                    Some(value) => Some(PathBuf::from(value)),
+                    None => return Err(format!("{} needs a file name", arg)),
+                };
+                batch = true;
+            },
+            _ => return Err(format!("Unknown argument: \"{}\"\n{}", arg, USAGE)),
+        }
+    }
+
+    // The daily puzzle has its own seed, so we can't have both. We look at the clock only once.
+    let daily = if daily {
+        if seed.is_some() {
+            return Err("--seed and --daily can't be used together".to_string());
+        }
+        let today = SystemTime::now();
+        seed = Some(daily::daily_seed(today));
+        Some(today)
+    } else {
+        None
+    };
+
+    // Both modes read the standard input their own way
+    if json && batch {
+        return Err("--json can't be used with --batch or --input".to_string());
     }
+    if serve && batch {
+        return Err("--serve can't be used with --batch or --input".to_string());
+    }
+
+    // In a duel the players make up the numbers, and they talk to each other in text
+    if duel && (evil || seed.is_some()) {
+        return Err("--duel can't be used with --evil, --seed or --daily".to_string());
+    }
+    if duel && (json || batch) {
+        return Err("--duel can't be used with --json, --batch or --input".to_string());
+    }
+
+    // The full screen is for one player at the terminal
+    if tui && (duel || json || batch || serve) {
+        return Err("--tui can't be used with --duel, --json, --batch or the server".to_string());
+    }
+
+    // Everybody in a race guesses the same number, and the evil host can't have one
+    if race && (duel || evil || json) {
+        return Err("--race can't be used with --duel, --evil or --json".to_string());
+    }
+
+    // The evil host has no secret number to seed
+    if evil && seed.is_some() {
+        return Err("--evil can't be used with --seed or --daily".to_string());
+    }
+
+    // Let the config check the rules. The map_err() turns ConfigError into a String message and
+    // the question mark operator returns it from our function if there was an error.
+    let config = GameConfig::with_repeats(length, alphabet, repeats).map_err(|error| error.to_string())?;
+
+    Ok(Options { config, reverse, strategy, tournament, seed, daily, hard, evil, batch, input, json,
+                 duel, race, tui, serve, host, port, max_sessions, idle, player, rules, report: true })
 }
 
-// This function just prints out the list of available game commands
-fn print_help() {
-    println!("r, restart    - Restart game");
+// A short description of command line arguments
+const USAGE: &str = "Usage: cowbull [--length 3..10] [--alphabet digits|hex|letters|<symbols>] [--repeats]
+               [--seed <number> | --daily | --evil] [--hard]
+               [--batch] [--input <file>] [--json] [--duel] [--race] [--tui]
+               [--serve] [--host <address>] [--port <number>] [--max-sessions <number>] [--idle <seconds>]
+               [--reverse | --tournament] [--strategy first|minimax|entropy|expected-size]
+               [--player <name>]";
+
+// This function just prints out the list of available game commands. We borrow the rules to tell
+// the player how long the code is.
+fn print_help(config: &GameConfig) {
+    println!("r, restart    - Give up and start a new game");
     println!("q, quit, exit - Quit game");
     println!("h, help, ?    - This text");
-    println!("s, stats      - Check out some hints on potential digit positions");
-    println!("<NNNN>        - Enter four unique digits to guess the number and win");
+    println!("s, hints      - Check out hints on digit positions: + is here, - is not,");
+    println!("                a percentage is a chance that the digit is here");
+    println!("stats         - Show how the games of this session went");
+    println!("stats --global - Show games played, win rate, streaks and tries of every game");
+    println!("history       - List your guesses with their answers next to the hints");
+    println!("save <FILE>   - Save the game to a file");
+    println!("load [FILE]   - Load a game from a file, or the auto-saved one");
+    println!("report [FILE] - Compare your guesses with the solver's, or write it to a file");
+    println!("export <FILE> - Write the transcript of the game to a file, secret number included,");
+    println!("                which keeps a seeded game off the leaderboard");
+    println!("replay <FILE> - Play a transcript back turn by turn, checking every answer");
+    println!("leaderboard [day|week|all] - Rank the players of this seed, or of all seeds");
+    println!("profile       - Show your profile");
+    println!("profile <NAME> - Switch to another player, a new profile is made if needed");
+    println!("profile set <SETTING> <VALUE> - Change a setting of your profile for the next time:");
+    println!("                {}", profile::SETTINGS);
+    println!("solve [NAME]  - Let the computer finish the game using a strategy:");
+    println!("                {} (minimax is the default)", solver::strategy_names());
+    println!("<{}>{}- Enter {} to guess the number and win",
+             "N".repeat(config.length()), " ".repeat(12 - config.length()), config.describe());
 }
 
-// This functions take a two-dimmensional array of special typed values (see more in the game
-// module description)
-fn print_hint(table: [[Hint; 4]; 10]) {
+// This functions takes the game and prints its two-dimmensional table of special typed values (see
+// more in the game module description). The table itself is built by hint_lines() below.
+fn print_hint(game: &Game) {
+    for line in hint_lines(game) {
+        println!("{}", line);
+    }
+}
 
-    // Print position numbers
-    println!("   1 2 3 4");
+// This function prints the history of guesses to the right of the hint table, so the player can
+// see both at once. Any of the two can be longer, so we pad the shorter one with empty lines.
+fn print_history(game: &Game) {
+    if game.history.is_empty() {
+        println!("No guesses yet");
+        return;
+    }
 
-    // Loop through 10 digits from 0 to 9 inclusively
-    for i in 0..10 {
+    let hints = hint_lines(game);
+    let history = history_lines(game);
 
-        // Print the digit
-        print!("{}: ", i);
+    // Every hint line has the same width: the symbol, a colon and four characters per position
+    let width = 2 + 4 * game.config.length();
 
-        // Then loop through four available positions
-        for j in 0..4 {
+    for i in 0..hints.len().max(history.len()) {
+        // The get() method returns None past the end of a vector, and then we print nothing
+        let left = hints.get(i).map(|line| line.as_str()).unwrap_or("");
+        let right = history.get(i).map(|line| line.as_str()).unwrap_or("");
+        println!("{:<width$}   {}", left, right, width = width);
+    }
+}
+
+// This function takes the game and builds its two-dimmensional table of special typed values as
+// lines of text. We need the whole game, not only the table, because the symbols are stored in its
+// rules.
+fn hint_lines(game: &Game) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    // Print position numbers. Every column is four characters wide, so there's enough room for
+    // a percentage.
+    let mut header = "  ".to_string();
+    for j in 0..game.config.length() {
+        header.push_str(&format!("{:>4}", j + 1));
+    }
+    lines.push(header);
+
+    // Loop through all symbols of the alphabet along with their lines of the table. The zip()
+    // adapter walks two iterators side by side.
+    for (symbol, hints) in game.config.symbols().iter().zip(game.hint_table.iter()) {
+
+        // Start with the symbol
+        let mut line = format!("{}:", symbol);
+
+        // Then loop through all available positions
+        for hint in hints {
 
-            // For each value of enum type Hint (see definition in game module) we print respective
-            // character
-            print!("{} ", match table[i][j] {
-                Hint::Unknown => " ",
-                Hint::Maybe   => "?",
-                Hint::Here    => "+",
-                Hint::NotHere => "-",
-            });
+            // For each value of enum type Hint (see definition in game module) we add respective
+            // character. The Maybe variant carries a share of possible numbers, which we show as
+            // a percentage. The "{:>3.0}%" format means three characters aligned to the right
+            // with no digits after the decimal point.
+            match *hint {
+                Hint::Unknown       => line.push_str("    "),
+                Hint::Maybe(chance) => line.push_str(&format!("{:>3.0}%", chance * 100.0)),
+                Hint::Here          => line.push_str("   +"),
+                Hint::NotHere       => line.push_str("   -"),
+            }
         }
 
-        // Print the new line character to finish line for this digit
-        print!("\n");
+        lines.push(line);
     }
+
+    lines
+}
+
+// This function builds the lines of the history table: the number of every guess, the guess itself,
+// its answer and when it was made, counting from the start of the game
+fn history_lines(game: &Game) -> Vec<String> {
+    let mut lines = vec![format!("  # {:<width$} C B  time", "guess", width = game.config.length().max(5))];
+
+    for (i, turn) in game.history.iter().enumerate() {
+        // The clock can go backwards, for example when it's set by hand, and then we show zero
+        let seconds = turn.time.duration_since(game.started).map(|elapsed| elapsed.as_secs()).unwrap_or(0);
+        lines.push(format!("{:>3} {:<width$} {} {} {:>2}:{:02}",
+                           i + 1, game.config.format_code(&turn.guess), turn.cows, turn.bulls,
+                           seconds / 60, seconds % 60, width = game.config.length().max(5)));
+    }
+
+    lines
 }
@@ -0,0 +1,466 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module keeps the profiles of the players who share one computer. A profile has a name,
+//! the rules the player likes and how the game should look for them. All profiles live in one
+//! plain text file:
+//!
+//! ```text
+//! cowbull-profiles 2
+//! profile alice
+//! rules 5 hex
+//! hard yes
+//! profile bob
+//! tui yes
+//! report no
+//! ```
+//!
+//! Every `profile` line starts a new profile, and the lines after it change its settings. A setting
+//! that isn't there keeps its default: the classic rules, the usual screen and a report after every
+//! win. The rules are the code length, the alphabet and the word `repeats` if symbols can repeat.
+//! They are on one line, because they only make sense together: three symbols can't fill a code of
+//! four without repeats. Custom symbols are written as `custom:<symbols>`, like in every other
+//! file. Version 1 wrote them as `custom <symbols>`, and we still read that.
+//!
+//! Two programs can change the file at the same time, for example when two players sit at two
+//! terminals. To keep them from overwriting each other, a program that wants to change the file
+//! first creates a lock file next to it. Only one program can create it, the others wait until
+//! it's gone. The new profiles are written to a temporary file, which then replaces the old one in
+//! a single step, so a reader never sees a half-written file, even without the lock.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use game::{Alphabet, GameConfig};
+use game::format::{HeaderError, alphabet_word, check_header, parse_rules, rules_words, write_header};
+
+// The first word of the profiles file
+const MAGIC: &str = "cowbull-profiles";
+
+/// The version of the format we write
+pub const PROFILES_VERSION: u32 = 2;
+
+// The oldest version we can still read
+const OLDEST_VERSION: u32 = 1;
+
+/// The longest name of a profile
+pub const MAX_NAME: usize = 20;
+
+// How long we wait for another program to let go of the lock, and how long we wait between tries
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+
+// A lock older than this was left by a program that has crashed. Nobody holds the lock for more
+// than a moment, so it's safe to break it.
+const STALE_LOCK: Duration = Duration::from_secs(30);
+
+/// Things that can go wrong with profiles
+#[derive(Debug)]
+pub enum ProfileError {
+    /// The file can't be read or written
+    Io(io::Error),
+    /// The file was written by a version of the game we can't read
+    Version(u32),
+    /// Something is wrong on a line of the file
+    Format(usize, String),
+    /// Another program holds the lock for too long
+    Locked,
+    /// The name can't be used for a profile
+    BadName(String),
+    /// There's no such setting, or the value doesn't fit it
+    BadSetting(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProfileError::Io(ref error) => write!(f, "{}", error),
+            ProfileError::Version(version) =>
+                write!(f, "Profiles format version {} is not supported, version {} is the latest", version, PROFILES_VERSION),
+            ProfileError::Format(line, ref message) => write!(f, "Bad profiles file, line {}: {}", line, message),
+            ProfileError::Locked => write!(f, "The profiles are being changed by another program, try again later"),
+            ProfileError::BadName(ref name) =>
+                write!(f, "\"{}\" can't be a name: use up to {} letters, digits, '-' and '_'", name, MAX_NAME),
+            ProfileError::BadSetting(ref message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for ProfileError {
+    fn from(error: io::Error) -> ProfileError {
+        ProfileError::Io(error)
+    }
+}
+
+/// The preferences of one player
+#[derive(Clone, Debug)]
+pub struct Profile {
+    /// The name of the player. It's also the name of the folder with the player's files, so it's
+    /// limited to letters, digits, '-' and '_' (see valid_name()).
+    pub name: String,
+    /// The rules the player likes
+    pub config: GameConfig,
+    /// True if the player likes hard mode
+    pub hard: bool,
+    /// True if the game should start on the full screen
+    pub tui: bool,
+    /// True if the game should show the analysis report after every win
+    pub report: bool,
+}
+
+/// The names of the settings change() knows, for messages
+pub const SETTINGS: &str = "length, alphabet, repeats, hard, tui, report";
+
+impl Profile {
+
+    /// A profile with the default settings
+    pub fn new(name: &str) -> Profile {
+        Profile { name: name.to_string(), config: GameConfig::default(), hard: false, tui: false, report: true }
+    }
+
+    /// Changes a setting by its name, the same way the file does. New rules are checked, so a
+    /// profile can't have rules that can't be played.
+    pub fn change(&mut self, key: &str, value: &str) -> Result<(), ProfileError> {
+        // Flags take yes or no. The closure returns the value or an error for this key.
+        let flag = || match value {
+            "yes" | "on" | "true" => Ok(true),
+            "no" | "off" | "false" => Ok(false),
+            _ => Err(ProfileError::BadSetting(format!("{} needs yes or no", key))),
+        };
+
+        let config = &self.config;
+        let (length, alphabet, repeats) = (config.length(), config.alphabet().clone(), config.repeats());
+        let rules = match key {
+            "length" => match value.parse() {
+                Ok(length) => (length, alphabet, repeats),
+                Err(_) => return Err(ProfileError::BadSetting("length needs a number".to_string())),
+            },
+            "alphabet" => (length, Alphabet::from_name(value), repeats),
+            "repeats" => (length, alphabet, flag()?),
+            "hard" => return flag().map(|hard| self.hard = hard),
+            "tui" => return flag().map(|tui| self.tui = tui),
+            "report" => return flag().map(|report| self.report = report),
+            _ => return Err(ProfileError::BadSetting(format!("Unknown setting \"{}\", try {}", key, SETTINGS))),
+        };
+
+        self.set_rules(rules.0, rules.1, rules.2)
+    }
+
+    // Changes the rules if they can be played
+    fn set_rules(&mut self, length: usize, alphabet: Alphabet, repeats: bool) -> Result<(), ProfileError> {
+        self.config = GameConfig::with_repeats(length, alphabet, repeats)
+            .map_err(|error| ProfileError::BadSetting(error.to_string()))?;
+        Ok(())
+    }
+
+    /// Describes the settings of the profile as lines for the player
+    pub fn describe(&self) -> Vec<String> {
+        let yes_no = |on| if on { "yes" } else { "no" };
+        vec![
+            format!("length   {}", self.config.length()),
+            format!("alphabet {}", alphabet_name(self.config.alphabet())),
+            format!("repeats  {}", yes_no(self.config.repeats())),
+            format!("hard     {}", yes_no(self.hard)),
+            format!("tui      {}", yes_no(self.tui)),
+            format!("report   {}", yes_no(self.report)),
+        ]
+    }
+
+    // Writes the profile to the file, leaving out the settings that have their default values
+    fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let default = Profile::new(&self.name);
+        writeln!(out, "profile {}", self.name)?;
+        if self.config != default.config {
+            writeln!(out, "rules {}", rules_words(&self.config))?;
+        }
+        for &(on, default, key) in &[(self.hard, default.hard, "hard"), (self.tui, default.tui, "tui"),
+                                     (self.report, default.report, "report")] {
+            if on != default {
+                writeln!(out, "{} {}", key, if on { "yes" } else { "no" })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The name of an alphabet, or its symbols if it's a custom one
+fn alphabet_name(alphabet: &Alphabet) -> String {
+    match alphabet.name() {
+        Some(name) => name.to_string(),
+        None => alphabet.symbols().iter().collect(),
+    }
+}
+
+/// Checks that a name can be used for a profile. The name becomes the name of a folder, so we
+/// keep it simple: no slashes, dots or spaces that could lead somewhere else.
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_NAME
+        && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// All the profiles of the computer
+#[derive(Default)]
+pub struct Profiles {
+    /// The profiles in the order they were made
+    pub list: Vec<Profile>,
+}
+
+impl Profiles {
+
+    /// Reads the profiles from anything that can be read line by line
+    pub fn load<R: BufRead>(input: R) -> Result<Profiles, ProfileError> {
+        let mut profiles = Profiles::default();
+        let mut version = PROFILES_VERSION;
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let number = i + 1;
+
+            if number == 1 {
+                match check_header(&line, MAGIC, OLDEST_VERSION..=PROFILES_VERSION) {
+                    Ok(found) => {
+                        version = found;
+                        continue;
+                    },
+                    Err(HeaderError::Version(version)) => return Err(ProfileError::Version(version)),
+                    Err(HeaderError::BadVersion) => return Err(format_error(number, "bad version")),
+                    Err(HeaderError::Foreign) => return Err(format_error(number, "this is not a profiles file")),
+                }
+            }
+
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+
+            match words.as_slice() {
+                [] => (),
+                ["profile", name] if valid_name(name) => profiles.list.push(Profile::new(name)),
+                ["profile", ..] => return Err(format_error(number, "bad name")),
+                // Version 1 had a space after the word custom
+                ["rules", length, "custom", symbols, rest @ ..] if version == 1 => {
+                    let alphabet = alphabet_word(&Alphabet::Custom(symbols.chars().collect()));
+                    rules(&mut profiles, number, length, &alphabet, rest)?
+                },
+                ["rules", length, alphabet, rest @ ..] => rules(&mut profiles, number, length, alphabet, rest)?,
+                [key @ ("hard" | "tui" | "report"), value] => change(&mut profiles, number, |profile| profile.change(key, value))?,
+                _ => return Err(format_error(number, "unknown record")),
+            }
+        }
+        Ok(profiles)
+    }
+
+    /// Writes all the profiles
+    pub fn save<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write_header(out, MAGIC, PROFILES_VERSION)?;
+        for profile in &self.list {
+            profile.write(out)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the profiles from a file. If there's no file yet, there are no profiles.
+    pub fn load_file(path: &Path) -> Result<Profiles, ProfileError> {
+        match File::open(path) {
+            Ok(file) => Profiles::load(BufReader::new(file)),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(Profiles::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Finds a profile by its name
+    pub fn find(&self, name: &str) -> Option<&Profile> {
+        self.list.iter().find(|profile| profile.name == name)
+    }
+
+    /// Finds a profile by its name, adding a new one with the default settings if there's none
+    pub fn find_or_add(&mut self, name: &str) -> Result<&mut Profile, ProfileError> {
+        if !valid_name(name) {
+            return Err(ProfileError::BadName(name.to_string()));
+        }
+        let index = match self.list.iter().position(|profile| profile.name == name) {
+            Some(index) => index,
+            None => {
+                self.list.push(Profile::new(name));
+                self.list.len() - 1
+            },
+        };
+        Ok(&mut self.list[index])
+    }
+}
+
+/// Changes the profiles in a file, safe from other programs that do the same. The file is read
+/// again under the lock, so the changes of others made in the meantime are kept. The closure
+/// makes the changes and returns what the caller needs, like the changed profile.
+pub fn update<T, F>(path: &Path, change: F) -> Result<T, ProfileError>
+    where F: FnOnce(&mut Profiles) -> Result<T, ProfileError> {
+    let _lock = Lock::take(path)?;
+
+    let mut profiles = Profiles::load_file(path)?;
+    let result = change(&mut profiles)?;
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    {
+        let mut file = File::create(&temporary)?;
+        profiles.save(&mut file)?;
+        file.sync_all()?;
+    }
+
+    // Renaming a file replaces the old one at once, readers see either the old file or the new one
+    fs::rename(&temporary, path)?;
+    Ok(result)
+}
+
+/// Finds a profile by its name, adding it to the file if it's new
+pub fn open(path: &Path, name: &str) -> Result<Profile, ProfileError> {
+    // Most of the time the profile is there, and we don't need the lock to read it
+    if let Some(profile) = Profiles::load_file(path)?.find(name) {
+        return Ok(profile.clone());
+    }
+    update(path, |profiles| profiles.find_or_add(name).map(|profile| profile.clone()))
+}
+
+// Applies a setting from the file to the latest profile
+fn change<F>(profiles: &mut Profiles, line: usize, setting: F) -> Result<(), ProfileError>
+    where F: FnOnce(&mut Profile) -> Result<(), ProfileError> {
+    match profiles.list.last_mut() {
+        Some(profile) => setting(profile).map_err(|error| format_error(line, &error.to_string())),
+        None => Err(format_error(line, "a setting before the first profile")),
+    }
+}
+
+// Applies a rules line from the file to the latest profile
+fn rules(profiles: &mut Profiles, line: usize, length: &str, alphabet: &str, rest: &[&str]) -> Result<(), ProfileError> {
+    if !matches!(rest, [] | ["repeats"]) {
+        return Err(format_error(line, "unknown record"));
+    }
+    let config = parse_rules(length, alphabet, rest).map_err(|message| format_error(line, &message))?;
+    change(profiles, line, |profile| {
+        profile.config = config;
+        Ok(())
+    })
+}
+
+fn format_error(line: usize, message: &str) -> ProfileError {
+    ProfileError::Format(line, message.to_string())
+}
+
+// The lock of the profiles file. It's a file next to it that exists while somebody changes the
+// profiles. The lock is let go when the value is dropped.
+struct Lock(PathBuf);
+
+impl Lock {
+
+    fn take(path: &Path) -> Result<Lock, ProfileError> {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".lock");
+        let lock = PathBuf::from(name);
+
+        let start = SystemTime::now();
+        loop {
+            // create_new() fails if the file exists. The system checks and creates it in a single
+            // step, so two programs can't both succeed.
+            match OpenOptions::new().write(true).create_new(true).open(&lock) {
+                Ok(_) => return Ok(Lock(lock)),
+                Err(ref error) if error.kind() == io::ErrorKind::AlreadyExists => (),
+                Err(error) => return Err(error.into()),
+            }
+
+            // A lock left by a crashed program would stop everybody forever, so an old one is
+            // removed. If it's gone already, somebody else has done that for us.
+            let age = fs::metadata(&lock)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+            if age.is_some_and(|age| age > STALE_LOCK) {
+                let _ = fs::remove_file(&lock);
+                continue;
+            }
+
+            if SystemTime::now().duration_since(start).unwrap_or_default() > LOCK_TIMEOUT {
+                return Err(ProfileError::Locked);
+            }
+            thread::sleep(LOCK_RETRY);
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+    use std::thread;
+
+    use super::{Profiles, update};
+    use game::{Alphabet, GameConfig};
+
+    // Many players make their profiles at once. The lock must keep every one of them, and nobody
+    // may see a broken file.
+    #[test]
+    fn concurrent_updates() {
+        let path = env::temp_dir().join(format!("cowbull-profiles-test-{}", process::id()));
+        let _ = fs::remove_file(&path);
+
+        let threads = (0..8)
+            .map(|i| {
+                let path = path.clone();
+                thread::spawn(move || for j in 0..5 {
+                    let name = format!("p{}-{}", i, j);
+                    update(&path, |profiles| profiles.find_or_add(&name).map(|_| ())).unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let profiles = Profiles::load_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(profiles.list.len(), 40);
+        for i in 0..8 {
+            for j in 0..5 {
+                assert!(profiles.find(&format!("p{}-{}", i, j)).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut profiles = Profiles::default();
+        profiles.find_or_add("alice").unwrap().change("alphabet", "hex").unwrap();
+        let bob = profiles.find_or_add("bob").unwrap();
+        bob.config = GameConfig::with_repeats(3, Alphabet::Custom("xyz".chars().collect()), true).unwrap();
+        bob.tui = true;
+
+        let mut file = Vec::new();
+        profiles.save(&mut file).unwrap();
+        let loaded = Profiles::load(&file[..]).unwrap();
+        assert_eq!(loaded.list.len(), 2);
+        assert_eq!(loaded.find("alice").unwrap().config, profiles.list[0].config);
+        assert_eq!(loaded.find("bob").unwrap().config, profiles.list[1].config);
+        assert!(loaded.find("bob").unwrap().tui);
+    }
+
+    #[test]
+    fn version_one_is_still_read() {
+        let file = "cowbull-profiles 1\nprofile alice\nrules 3 custom abc repeats\nhard yes\n";
+        let profiles = Profiles::load(file.as_bytes()).unwrap();
+        let alice = profiles.find("alice").unwrap();
+        assert_eq!(alice.config, GameConfig::with_repeats(3, Alphabet::Custom("abc".chars().collect()), true).unwrap());
+        assert!(alice.hard);
+
+        // Version 2 reads the same line as an alphabet called custom
+        assert!(Profiles::load("cowbull-profiles 2\nprofile alice\nrules 3 custom abc\n".as_bytes()).is_err());
+    }
+}
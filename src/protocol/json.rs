@@ -0,0 +1,409 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// A tiny JSON reader and writer, just enough for the protocol. JSON is simple enough to parse by
+// hand: a value is null, true, false, a number, a string, an array of values or an object that
+// maps names to values. We read it with a recursive descent parser, which has one function per
+// kind of value and calls them from each other, the same way the values are nested.
+//
+// Every nested array or object is one more call on the stack, so a line of a million '[' would
+// use up the whole stack and crash the program. We count how deep we are and give up past
+// MAX_DEPTH, which is a lot more than any request of the protocol needs.
+
+use std::fmt;
+
+// How many arrays and objects can be nested in each other
+pub const MAX_DEPTH: usize = 32;
+
+// A JSON value. Objects keep their fields in a vector instead of a map, so we write them in the
+// same order they were added, which makes the output easier to read.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+
+    // Parses a whole text as one value. Anything but spaces after the value is an error.
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser { chars: text.chars().collect(), position: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.skip_spaces();
+        match parser.peek() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected '{}' after the value", c)),
+        }
+    }
+
+    // Returns the field of an object, or None if there's no such field or this is not an object
+    pub fn get(&self, name: &str) -> Option<&Json> {
+        match *self {
+            Json::Object(ref fields) => fields.iter().find(|field| field.0 == name).map(|field| &field.1),
+            _ => None,
+        }
+    }
+
+    // Returns the text of a string value
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Json::String(ref value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+// These conversions let us build values with .into() instead of naming the variant every time
+impl From<bool> for Json {
+    fn from(value: bool) -> Json {
+        Json::Bool(value)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<u8> for Json {
+    fn from(value: u8) -> Json {
+        Json::Number(value as f64)
+    }
+}
+
+impl<'a> From<&'a str> for Json {
+    fn from(value: &'a str) -> Json {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Json {
+        Json::String(value)
+    }
+}
+
+// Writes the value as compact JSON on a single line, which is what JSON lines need
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            // Whole numbers are written without a fraction, so a count of 3 is 3 and not 3.0
+            Json::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => write!(f, "{}", value as i64),
+            Json::Number(value) if value.is_finite() => write!(f, "{}", value),
+            // JSON has no infinity, so we have to write something else
+            Json::Number(_) => write!(f, "null"),
+            Json::String(ref value) => write_string(f, value),
+            Json::Array(ref values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(ref fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+// Writes a string in quotes. Quotes, backslashes and control characters must be escaped.
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+// The state of the parser: the text, the position of the next character in it and how many
+// arrays and objects we're in
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some(' ') | Some('\t') | Some('\n') | Some('\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    // Checks that the text has the given word next, like "true" or "null"
+    fn word(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(format!("expected {}", word));
+            }
+        }
+        Ok(value)
+    }
+
+    // Reads any value. The first character tells what kind of value it is.
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('n') => self.word("null", Json::Null),
+            Some('t') => self.word("true", Json::Bool(true)),
+            Some('f') => self.word("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') | Some('{') => self.nested(),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("unexpected '{}'", c)),
+            None => Err("unexpected end of the text".to_string()),
+        }
+    }
+
+    // Reads a number. We collect the characters a number can have and let Rust parse them, after
+    // checking the few things JSON is stricter about.
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        let text = self.chars[start..self.position].iter().collect::<String>();
+
+        // JSON doesn't allow "01", ".5" or "5." even though Rust would read them
+        let digits = text.trim_start_matches('-');
+        let leading_zero = digits.len() > 1 && digits.starts_with('0') && digits.chars().nth(1).is_some_and(|c| c.is_ascii_digit());
+        if leading_zero || !digits.starts_with(|c: char| c.is_ascii_digit()) || text.contains(".e") || text.ends_with('.') {
+            return Err(format!("bad number {}", text));
+        }
+        text.parse::<f64>().map(Json::Number).map_err(|_| format!("bad number {}", text))
+    }
+
+    // Reads a string in quotes, turning escapes back into characters
+    fn string(&mut self) -> Result<String, String> {
+        self.next();
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                None => return Err("the string is not closed".to_string()),
+                Some('"') => return Ok(value),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode()?,
+                        _ => return Err("bad escape in a string".to_string()),
+                    };
+                    value.push(c);
+                },
+                Some(c) if (c as u32) < 0x20 => return Err("control character in a string".to_string()),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    // Reads the four hexadecimal digits of a \u escape. Characters outside of the basic plane are
+    // written as two escapes in a row, called a surrogate pair, and we have to glue them together.
+    fn unicode(&mut self) -> Result<char, String> {
+        let first = self.hex()?;
+        let code = if (0xd800..0xdc00).contains(&first) {
+            if self.next() != Some('\\') || self.next() != Some('u') {
+                return Err("unpaired surrogate in a string".to_string());
+            }
+            let second = self.hex()?;
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err("unpaired surrogate in a string".to_string());
+            }
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        } else {
+            first
+        };
+        ::std::char::from_u32(code).ok_or_else(|| "bad character in a string".to_string())
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.next().and_then(|c| c.to_digit(16)).ok_or_else(|| "bad \\u escape in a string".to_string())?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    // Reads an array or an object, one level deeper than we are
+    fn nested(&mut self) -> Result<Json, String> {
+        if self.depth == MAX_DEPTH {
+            return Err(format!("values are nested deeper than {} levels", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let value = if self.peek() == Some('[') { self.array() } else { self.object() };
+        self.depth -= 1;
+        value
+    }
+
+    // Reads an array of values separated by commas
+    fn array(&mut self) -> Result<Json, String> {
+        self.next();
+        let mut values = Vec::new();
+        self.skip_spaces();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_spaces();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return Err("expected , or ] in an array".to_string()),
+            }
+        }
+    }
+
+    // Reads an object of name and value pairs separated by commas
+    fn object(&mut self) -> Result<Json, String> {
+        self.next();
+        let mut fields = Vec::new();
+        self.skip_spaces();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_spaces();
+            if self.peek() != Some('"') {
+                return Err("expected a field name in quotes".to_string());
+            }
+            let name = self.string()?;
+            self.skip_spaces();
+            if self.next() != Some(':') {
+                return Err("expected : after a field name".to_string());
+            }
+            fields.push((name, self.value()?));
+            self.skip_spaces();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return Err("expected , or } in an object".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Json, MAX_DEPTH};
+
+    #[test]
+    fn parses_every_kind_of_value() {
+        let value = Json::parse(r#" {"cmd": "guess", "value": "0123", "id": [1, -2.5, 3e2, true, false, null]} "#).unwrap();
+        assert_eq!(value.get("cmd").and_then(|cmd| cmd.as_str()), Some("guess"));
+        assert_eq!(value.get("value").and_then(|cmd| cmd.as_str()), Some("0123"));
+        assert_eq!(value.get("id"), Some(&Json::Array(vec![
+            Json::Number(1.0), Json::Number(-2.5), Json::Number(300.0), Json::Bool(true), Json::Bool(false), Json::Null,
+        ])));
+        assert_eq!(value.get("missing"), None);
+    }
+
+    #[test]
+    fn rejects_broken_text() {
+        for text in &["", "{", "[1,]", "{\"a\"}", "\"open", "tru", "1 2", "{\"a\":1,}", "\"\\x\"", "\"a\u{1}\""] {
+            assert!(Json::parse(text).is_err(), "{:?} was accepted", text);
+        }
+    }
+
+    #[test]
+    fn reads_escapes() {
+        let value = Json::parse(r#""a\"b\\c\/d\n\t\u0041\ud83d\ude00""#).unwrap();
+        assert_eq!(value, Json::String("a\"b\\c/d\n\tA\u{1f600}".to_string()));
+        assert!(Json::parse(r#""\ud83d""#).is_err());
+        assert!(Json::parse(r#""\u12""#).is_err());
+    }
+
+    #[test]
+    fn writes_escapes() {
+        let value = Json::from("say \"hi\"\\\n\r\t\u{1}");
+        assert_eq!(value.to_string(), r#""say \"hi\"\\\n\r\t\u0001""#);
+    }
+
+    // Whatever we write, we must be able to read back
+    #[test]
+    fn round_trip() {
+        let value = Json::Object(vec![
+            ("text".to_string(), "quotes \" and \\ and \u{7f} and \u{1f600}".into()),
+            ("count".to_string(), 3u32.into()),
+            ("chance".to_string(), Json::Number(0.125)),
+            ("list".to_string(), Json::Array(vec![Json::Null, true.into(), Json::Array(Vec::new())])),
+        ]);
+        assert!(value.to_string().ends_with(r#""count":3,"chance":0.125,"list":[null,true,[]]}"#));
+        assert_eq!(Json::parse(&value.to_string()), Ok(value));
+    }
+
+    #[test]
+    fn depth_is_capped() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(Json::parse(&nested(MAX_DEPTH + 1)).is_err());
+
+        // Objects count too, and so do arrays and objects mixed
+        let objects = format!("{}1{}", "{\"a\":".repeat(MAX_DEPTH + 1), "}".repeat(MAX_DEPTH + 1));
+        assert!(Json::parse(&objects).is_err());
+        let mixed = |pairs: usize| format!("{}1{}", "[{\"a\":".repeat(pairs), "}]".repeat(pairs));
+        assert!(Json::parse(&mixed(MAX_DEPTH / 2)).is_ok());
+        assert!(Json::parse(&mixed(MAX_DEPTH / 2 + 1)).is_err());
+
+        // A line much deeper than the stack could take is refused, not a crash
+        assert!(Json::parse(&"[".repeat(1_000_000)).is_err());
+    }
+}
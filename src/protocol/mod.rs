@@ -0,0 +1,297 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// This module lets bots play the game. A bot sends one JSON object per line and gets one JSON
+// object per line back, so it doesn't have to scrape the text we print for humans. Any language
+// that can start a program and read its output can play this way.
+//
+// Every request has a "cmd" field and may have an "id" field, which is copied to the response,
+// so a bot can match the answers to its requests. The commands are:
+//
+//     {"cmd":"guess","value":"0123"}  scores a guess
+//     {"cmd":"hints"}                 returns the hint table
+//     {"cmd":"history"}               returns every guess of the game with its answer
+//     {"cmd":"rules"}                 returns the rules of the game
+//     {"cmd":"new"}                   gives up the game and starts a new one
+//     {"cmd":"quit"}                  ends the session
+//
+// Every response has an "ok" field. A guess is answered with its cows and bulls, the number of
+// tries and a win flag, like {"ok":true,"guess":"0123","cows":1,"bulls":2,"tries":3,"win":false}.
+// After a win the next game starts right away, same as when a human plays. An error looks like
+// {"ok":false,"error":"repeated_symbol","message":"Digits must be unique, '1' is repeated"},
+// where the error code is one of the words in error_code() and contradiction_code() below, or
+// bad_json, bad_request, unknown_command and missing_value for requests we can't make sense of.
+
+use std::io::{self, BufRead, Read, Write};
+
+use cowbull::game::{Contradiction, Guess, GuessError, Hint};
+
+use session::Session;
+
+pub mod json;
+use self::json::Json;
+
+// The longest line we read. A request of the protocol fits into a few dozen characters, so a
+// longer line is a mistake or an attack, and either way we don't want to parse it.
+pub const MAX_LINE: usize = 4096;
+
+// Reads a line without the line break, or returns None when the input is over. The lines()
+// method would keep reading until it finds a line break, so somebody who never sends one could
+// make us fill the whole memory. The take() method stops reading after MAX_LINE bytes, and if
+// there's no line break by then, we fail with InvalidData, which the server takes as a reason to
+// hang up. The server and its duel and race modes read everything through this function.
+pub fn read_line<R: BufRead>(input: &mut R) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    if input.by_ref().take(MAX_LINE as u64 + 1).read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && line.len() > MAX_LINE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("the line is longer than {} bytes", MAX_LINE)));
+    }
+
+    // The same line breaks lines() would strip, with or without a carriage return
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+// Plays with a bot until it quits or the input is over. Only a failure to write the answer
+// stops us early, because then there's nobody to talk to anymore, and a line that is too long,
+// because a bot that sends one is broken and we can't tell where its next request starts.
+pub fn serve<R: BufRead, W: Write>(session: &mut Session, mut input: R, output: &mut W) -> io::Result<()> {
+    loop {
+        let line = match read_line(&mut input) {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(error) => {
+                if error.kind() == io::ErrorKind::InvalidData {
+                    writeln!(output, "{}", failure(None, Failure::new("bad_json", &error.to_string())))?;
+                    output.flush()?;
+                }
+                return Err(error);
+            },
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (response, quit) = respond(session, &line);
+        writeln!(output, "{}", response)?;
+        output.flush()?;
+
+        if quit {
+            return Ok(());
+        }
+    }
+
+    // The bot has left without saying goodbye, the game is abandoned
+    session.finish();
+    Ok(())
+}
+
+// What a bot can ask us to do
+enum Request {
+    Guess(String),
+    Hints,
+    History,
+    Rules,
+    New,
+    Quit,
+}
+
+// An error to tell the bot about: a code for the bot and a message for the human behind it
+struct Failure {
+    code: &'static str,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: &'static str, message: &str) -> Failure {
+        Failure { code, message: message.to_string() }
+    }
+}
+
+// Answers one line of input. The second value of the result tells if the bot wants to quit.
+fn respond(session: &mut Session, line: &str) -> (Json, bool) {
+    let value = match Json::parse(line) {
+        Ok(value) => value,
+        Err(message) => return (failure(None, Failure::new("bad_json", &message)), false),
+    };
+    let id = value.get("id").cloned();
+
+    let request = match parse_request(&value) {
+        Ok(request) => request,
+        Err(error) => return (failure(id, error), false),
+    };
+
+    let quit = matches!(request, Request::Quit);
+    let response = match handle(session, request) {
+        Ok(fields) => success(id, fields),
+        Err(error) => failure(id, error),
+    };
+    (response, quit)
+}
+
+// Figures out what the request wants
+fn parse_request(value: &Json) -> Result<Request, Failure> {
+    let command = match *value {
+        Json::Object(_) => value.get("cmd")
+            .and_then(|command| command.as_str())
+            .ok_or_else(|| Failure::new("bad_request", "the request has no \"cmd\" string"))?,
+        _ => return Err(Failure::new("bad_request", "the request must be an object")),
+    };
+
+    match command {
+        "guess" => match value.get("value").and_then(|guess| guess.as_str()) {
+            Some(guess) => Ok(Request::Guess(guess.to_string())),
+            None => Err(Failure::new("missing_value", "the guess needs a \"value\" string")),
+        },
+        "hints" => Ok(Request::Hints),
+        "history" => Ok(Request::History),
+        "rules" => Ok(Request::Rules),
+        "new" => Ok(Request::New),
+        "quit" => Ok(Request::Quit),
+        _ => Err(Failure::new("unknown_command", &format!("unknown command \"{}\"", command))),
+    }
+}
+
+// Does what the request wants and returns the fields of the answer
+fn handle(session: &mut Session, request: Request) -> Result<Vec<(String, Json)>, Failure> {
+    match request {
+        Request::Guess(value) => guess(session, &value),
+
+        // The hints are only figured out when they're asked for, since it takes a while
+        Request::Hints => {
+            session.game.analyze();
+            let game = &session.game;
+            let symbols = game.config.symbols().iter().map(|symbol| symbol.to_string().into()).collect();
+            let table = game.hint_table.iter()
+                .map(|line| Json::Array(line.iter().map(|&hint| hint_value(hint)).collect()))
+                .collect();
+            Ok(vec![field("symbols", Json::Array(symbols)), field("hints", Json::Array(table))])
+        },
+
+        Request::History => {
+            let game = &session.game;
+            let turns = game.history.iter()
+                .map(|turn| Json::Object(vec![
+                    field("guess", game.config.format_code(&turn.guess).into()),
+                    field("cows", turn.cows.into()),
+                    field("bulls", turn.bulls.into()),
+                ]))
+                .collect();
+            Ok(vec![field("turns", Json::Array(turns))])
+        },
+
+        Request::Rules => Ok(rules(session)),
+
+        Request::New => {
+            session.restart();
+            Ok(rules(session))
+        },
+
+        Request::Quit => {
+            session.finish();
+            Ok(Vec::new())
+        },
+    }
+}
+
+// Scores a guess, the same way the main loop does for humans
+fn guess(session: &mut Session, value: &str) -> Result<Vec<(String, Json)>, Failure> {
+    let guess = Guess::parse(value, &session.game.config)
+        .map_err(|error| Failure { code: error_code(&error), message: error.to_string() })?;
+    session.game.check_hard(&guess)
+        .map_err(|contradiction| Failure { code: contradiction_code(&contradiction), message: contradiction.to_string() })?;
+
+    let code = session.game.config.format_code(guess.code());
+    let (cows, bulls, win) = session.game.play(&guess);
+    let tries = session.game.tries;
+    if win {
+        session.win();
+    }
+    Ok(answer(code, cows, bulls, tries, win))
+}
+
+fn answer(code: String, cows: u8, bulls: u8, tries: u32, win: bool) -> Vec<(String, Json)> {
+    vec![
+        field("guess", code.into()),
+        field("cows", cows.into()),
+        field("bulls", bulls.into()),
+        field("tries", tries.into()),
+        field("win", win.into()),
+    ]
+}
+
+// The rules of the current game and how far it has gone
+fn rules(session: &Session) -> Vec<(String, Json)> {
+    let game = &session.game;
+    vec![
+        field("length", game.config.length().into()),
+        field("symbols", game.config.symbols().iter().collect::<String>().into()),
+        field("repeats", game.config.repeats().into()),
+        field("hard", game.hard.into()),
+        field("evil", game.is_evil().into()),
+        field("tries", game.tries.into()),
+    ]
+}
+
+// A hint is a chance that the symbol is at the position: 1 if it surely is, 0 if it surely isn't
+// and null if we don't know. Chances are rounded, nobody needs more than three digits of them.
+fn hint_value(hint: Hint) -> Json {
+    match hint {
+        Hint::Unknown => Json::Null,
+        Hint::Here => Json::Number(1.0),
+        Hint::NotHere => Json::Number(0.0),
+        Hint::Maybe(chance) => Json::Number((chance as f64 * 1000.0).round() / 1000.0),
+    }
+}
+
+// The error codes of invalid guesses. They are part of the protocol, so they must never change.
+fn error_code(error: &GuessError) -> &'static str {
+    match *error {
+        GuessError::WrongLength(..) => "wrong_length",
+        GuessError::NotADigit(..) => "not_a_digit",
+        GuessError::OutsideAlphabet(..) => "outside_alphabet",
+        GuessError::RepeatedDigit(..) | GuessError::RepeatedSymbol(..) => "repeated_symbol",
+    }
+}
+
+// Hard mode has a code for every way a guess can contradict an earlier answer
+fn contradiction_code(contradiction: &Contradiction) -> &'static str {
+    match *contradiction {
+        Contradiction::TooManyBulls(..) => "too_many_bulls",
+        Contradiction::TooFewBulls(..) => "too_few_bulls",
+        Contradiction::TooManyCommon(..) => "too_many_common",
+        Contradiction::TooFewCommon(..) => "too_few_common",
+    }
+}
+
+fn field(name: &str, value: Json) -> (String, Json) {
+    (name.to_string(), value)
+}
+
+// Builds a response. The id of the request goes first, if there was one, and then the ok flag.
+fn response(id: Option<Json>, ok: bool, fields: Vec<(String, Json)>) -> Json {
+    let mut response = Vec::new();
+    if let Some(id) = id {
+        response.push(field("id", id));
+    }
+    response.push(field("ok", ok.into()));
+    response.extend(fields);
+    Json::Object(response)
+}
+
+fn success(id: Option<Json>, fields: Vec<(String, Json)>) -> Json {
+    response(id, true, fields)
+}
+
+fn failure(id: Option<Json>, error: Failure) -> Json {
+    response(id, false, vec![field("error", error.code.into()), field("message", error.message.into())])
+}
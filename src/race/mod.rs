@@ -0,0 +1,222 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// This module plays races on the server. Everybody in a race guesses the same secret number, but
+// every player has a game of their own with their own tries, history and hints (see
+// Game::share_secret). The server tells everybody when somebody has guessed the number, and the
+// players are ranked by their tries, and by their time when the tries are equal.
+//
+// A race takes new players until somebody guesses the number. After that the next player who
+// connects starts a new race, while the old one goes on until all its players have finished.
+//
+// The players of a race are served by different threads, so the race is kept behind a Mutex. Only
+// one thread at a time can lock it, so two players can't change it at once.
+
+use std::io::{self, BufRead, Write};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+
+use cowbull::game::{Game, GameConfig, Guess};
+
+use protocol;
+
+// Names longer than this are cut, so they don't break the standings
+const MAX_NAME: usize = 20;
+
+// A finished player: how many tries it took and how long
+#[derive(Copy, Clone)]
+struct Finish {
+    tries: u32,
+    time: Duration,
+}
+
+// Everything the race knows about a player
+struct Racer {
+    name: String,
+    // Where to send the news of the race. Box<dyn Write + Send> can hold any writer that can be
+    // used from another thread, like a network connection.
+    output: Box<dyn Write + Send>,
+    finish: Option<Finish>,
+    // A player who has left doesn't get the news anymore
+    left: bool,
+}
+
+// A race: the game every player's game is a copy of, and the players
+pub struct Race {
+    game: Game,
+    racers: Vec<Racer>,
+}
+
+impl Race {
+
+    // Starts a race with a new secret number
+    pub fn new(config: GameConfig, seed: Option<u64>, hard: bool) -> Race {
+        let mut game = Game::new(config, seed);
+        game.hard = hard;
+        Race { game, racers: Vec::new() }
+    }
+
+    // A race that somebody has won doesn't take new players
+    pub fn is_won(&self) -> bool {
+        self.racers.iter().any(|racer| racer.finish.is_some())
+    }
+
+    // Adds a player to the race. Returns the number of the player in the race and the player's own
+    // game with the secret number of the race.
+    pub fn join(&mut self, name: &str, output: Box<dyn Write + Send>) -> (usize, Game) {
+        let index = self.racers.len();
+        let name = match name.chars().take(MAX_NAME).collect::<String>() {
+            ref name if name.is_empty() => format!("Player {}", index + 1),
+            name => name,
+        };
+
+        self.racers.push(Racer { name, output, finish: None, left: false });
+        let players = self.racers.iter().filter(|racer| !racer.left).count();
+        self.announce(&format!("{} has joined the race, {} players now", self.racers[index].name, players));
+
+        // Every race is made from a game with a fixed secret, so sharing it can't fail
+        let game = self.game.share_secret().expect("a race can't have the evil host");
+        (index, game)
+    }
+
+    // Records that a player has guessed the number and tells everybody how the race stands
+    pub fn finish(&mut self, index: usize, tries: u32, time: Duration) {
+        let first = !self.is_won();
+        self.racers[index].finish = Some(Finish { tries, time });
+
+        let name = self.racers[index].name.clone();
+        if first {
+            self.announce(&format!("{} is the first to guess the number, in {} tries and {}!", name, tries, clock(time)));
+        } else {
+            self.announce(&format!("{} has guessed the number in {} tries and {}", name, tries, clock(time)));
+        }
+        self.standings();
+    }
+
+    // Records that a player has left. A player who has finished stays in the standings.
+    pub fn leave(&mut self, index: usize) {
+        self.racers[index].left = true;
+        if self.racers[index].finish.is_none() {
+            let name = self.racers[index].name.clone();
+            self.announce(&format!("{} has left the race", name));
+        }
+    }
+
+    // Tells everybody the places of the players who have finished, and if the race is over
+    fn standings(&mut self) {
+        // Sort the finished players by tries and then by time. Tuples are compared field by field,
+        // which is exactly what we need.
+        let mut finished = self.racers.iter()
+            .filter_map(|racer| racer.finish.map(|finish| (finish.tries, finish.time, racer.name.clone())))
+            .collect::<Vec<(u32, Duration, String)>>();
+        finished.sort();
+
+        let over = self.racers.iter().all(|racer| racer.left || racer.finish.is_some());
+        self.announce(if over { "The race is over! Standings:" } else { "Standings so far:" });
+        for (place, &(tries, time, ref name)) in finished.iter().enumerate() {
+            self.announce(&format!("{:>3}. {} - {} tries, {}", place + 1, name, tries, clock(time)));
+        }
+    }
+
+    // Sends a line to every player who is still here. A player whose connection is broken will
+    // find out on their own thread, so we don't care about errors here.
+    fn announce(&mut self, message: &str) {
+        let line = format!("{}\n", message);
+        for racer in self.racers.iter_mut().filter(|racer| !racer.left) {
+            let _ = racer.output.write_all(line.as_bytes()).and_then(|_| racer.output.flush());
+        }
+    }
+}
+
+// Locks the race. If another thread has panicked while holding the lock, the mutex is "poisoned",
+// but a race has nothing that could be left broken halfway, so we just go on.
+pub fn lock(race: &Mutex<Race>) -> MutexGuard<'_, Race> {
+    race.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Plays the race for one player. The news of the race are written to the player by other threads
+// through the writer given to join(), so our own output must go to the same place.
+pub fn play<R: BufRead, W: Write>(race: &Mutex<Race>, mut input: R, output: &mut W,
+                                   news: Box<dyn Write + Send>) -> io::Result<()> {
+    write!(output, "Enter your name > ")?;
+    output.flush()?;
+    let name = match protocol::read_line(&mut input)? {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+
+    let (index, mut game) = lock(race).join(name.trim(), news);
+    let result = race_loop(race, index, &mut game, input, output);
+    lock(race).leave(index);
+    result
+}
+
+// Reads the commands and guesses of a player until the player leaves
+fn race_loop<R: BufRead, W: Write>(race: &Mutex<Race>, index: usize, game: &mut Game, mut input: R, output: &mut W) -> io::Result<()> {
+    writeln!(output, "Race! Guess the number of {} before the others (Enter 'q' to quit, 'h' for help)",
+             game.config.describe())?;
+    if game.hard {
+        writeln!(output, "Hard mode: every guess must agree with all earlier answers")?;
+    }
+    output.flush()?;
+
+    let mut finished = false;
+    while let Some(line) = protocol::read_line(&mut input)? {
+        match line.trim() {
+            "" => continue,
+            "q" | "quit" | "exit" => {
+                writeln!(output, "Bye")?;
+                return output.flush();
+            },
+            "h" | "help" | "?" => {
+                writeln!(output, "q, quit, exit - Leave the race")?;
+                writeln!(output, "h, help, ?    - This text")?;
+                writeln!(output, "s, hints      - Check out hints on symbol positions")?;
+                writeln!(output, "history       - List your guesses with their answers")?;
+                writeln!(output, "<guess>       - Enter {} to guess the number", game.config.describe())?;
+            },
+            "s" | "hints" => for line in ::hint_lines(game) {
+                writeln!(output, "{}", line)?;
+            },
+            "history" => for line in ::history_lines(game) {
+                writeln!(output, "{}", line)?;
+            },
+            _ if finished => writeln!(output, "You've finished the race, enter 'q' to leave")?,
+            input => match Guess::parse(input, &game.config) {
+                Err(error) => writeln!(output, "{}", error)?,
+                Ok(guess) => {
+                    if let Err(contradiction) = game.check_hard(&guess) {
+                        writeln!(output, "{}", contradiction)?;
+                    } else {
+                        let (cows, bulls, won) = game.play(&guess);
+                        if won {
+                            // The race tells everybody, this player included
+                            let time = SystemTime::now().duration_since(game.started).unwrap_or_default();
+                            lock(race).finish(index, game.tries, time);
+                            finished = true;
+                        } else {
+                            game.analyze();
+                            if cows == 0 && bulls == 0 {
+                                writeln!(output, "Nothing found")?;
+                            } else {
+                                writeln!(output, "Found {} cows and {} bulls", cows, bulls)?;
+                            }
+                        }
+                    }
+                },
+            },
+        }
+        output.flush()?;
+    }
+
+    // The player has hung up
+    Ok(())
+}
+
+// Formats a time as minutes and seconds
+fn clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
@@ -0,0 +1,200 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// This module lets us swap the roles. The human makes up a secret number and keeps it somewhere
+// safe, and the computer makes guesses. The human tells how many cows and bulls every guess has,
+// and the computer uses the solver to pick the next guess. Humans make mistakes while counting,
+// so we also try to tell which answer was wrong when the answers contradict each other.
+
+use std::io::{stdin, stdout};
+use std::io::Write;
+use std::time::SystemTime;
+
+use cowbull::game::{GameConfig, Turn};
+use cowbull::solver::{Builtin, Solver, Strategy, find_mistakes};
+
+// We list possible secret numbers after every answer, unless there are more than this
+const SHOW_CANDIDATES: usize = 10;
+
+// Plays the reverse game with the given rules until the computer wins or the human quits
+pub fn play(config: &GameConfig, strategy: &dyn Strategy) {
+    println!("Think of a number of {} ({}) and I'll guess it!",
+             config.describe(), config.symbols().iter().collect::<String>());
+    println!("Score my guesses as \"<cows> <bulls>\". Enter 'q' to quit, 'h' for help");
+
+    // We keep every answer the human gave us. The solver is rebuilt from this list whenever an
+    // answer is fixed, so a mistake in the past doesn't stay with us forever.
+    let mut turns: Vec<Turn> = Vec::new();
+
+    loop {
+        // Replay all answers to find out what is still possible. The only error the solver can
+        // give us is that there are too many codes, and then there's nothing we can do.
+        let solver = match Solver::from_turns(config, &turns, strategy) {
+            Ok(solver) => solver,
+            Err(error) => {
+                println!("{}", error);
+                return;
+            },
+        };
+
+        // If nothing is possible, the human has made a mistake. We explain it and wait for a fix.
+        let guess = match solver.next_guess() {
+            Some(guess) => guess,
+            None => {
+                explain_mistake(config, &turns);
+                match read_command(config, &mut turns, None) {
+                    Command::Quit => return,
+                    Command::Continue => continue,
+                }
+            },
+        };
+
+        println!("Guess {}: {}", turns.len() + 1, config.format_code(&guess));
+
+        match read_command(config, &mut turns, Some(&guess)) {
+            Command::Quit => return,
+            Command::Continue => (),
+        }
+
+        // The human has told us that every digit is a bull, so the game is over
+        if turns.last().map(|turn| turn.bulls as usize) == Some(config.length()) {
+            println!("I've guessed your number in {} tries!", turns.len());
+            return;
+        }
+
+        // Tell the human how the search is going. If nothing is possible, we'll explain the
+        // mistake on the next round of the loop instead.
+        if let Ok(solver) = Solver::from_turns(config, &turns, strategy) {
+            if !solver.candidates().is_empty() {
+                print_candidates(config, solver.candidates(), SHOW_CANDIDATES);
+            }
+        }
+    }
+}
+
+// What to do after reading a line of input
+enum Command {
+    Quit,
+    Continue,
+}
+
+// Reads input until the human either quits, scores the guess or fixes one of the older answers.
+// When there's no guess to score, only a fix or quit gets us out of here.
+fn read_command(config: &GameConfig, turns: &mut Vec<Turn>, guess: Option<&Vec<u8>>) -> Command {
+    loop {
+        let mut input = String::new();
+        print!("> ");
+        let _ = stdout().flush();
+
+        // If the input is over, there's nobody to play with
+        match stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => return Command::Quit,
+            Ok(_) => (),
+        }
+
+        // Split the input into words, so "1 2" becomes ["1", "2"]
+        let words = input.split_whitespace().collect::<Vec<&str>>();
+
+        match words.as_slice() {
+            [] => continue,
+            ["q"] | ["quit"] | ["exit"] => return Command::Quit,
+            ["h"] | ["help"] | ["?"] => print_help(),
+
+            // List every number that is still possible
+            ["l"] | ["list"] => match Solver::from_turns(config, turns, &Builtin::First) {
+                Ok(solver) => print_candidates(config, solver.candidates(), usize::MAX),
+                Err(error) => println!("{}", error),
+            },
+
+            // Replace the answer to one of the earlier guesses
+            ["f", number, cows, bulls] | ["fix", number, cows, bulls] => {
+                let index = match number.parse::<usize>() {
+                    Ok(number) if number >= 1 && number <= turns.len() => number - 1,
+                    _ => {
+                        println!("There's no guess number {}", number);
+                        continue;
+                    },
+                };
+                match parse_answer(config, cows, bulls) {
+                    Ok((cows, bulls)) => {
+                        turns[index].cows = cows;
+                        turns[index].bulls = bulls;
+                        println!("Guess {} ({}) now has {} cows and {} bulls",
+                                 index + 1, config.format_code(&turns[index].guess), cows, bulls);
+                        return Command::Continue;
+                    },
+                    Err(message) => println!("{}", message),
+                }
+            },
+
+            // Anything that looks like two numbers is an answer to the current guess
+            [cows, bulls] => match guess {
+                Some(guess) => match parse_answer(config, cows, bulls) {
+                    Ok((cows, bulls)) => {
+                        turns.push(Turn { guess: guess.clone(), cows, bulls, time: SystemTime::now() });
+                        return Command::Continue;
+                    },
+                    Err(message) => println!("{}", message),
+                },
+                None => println!("Fix one of the answers first: fix <guess> <cows> <bulls>"),
+            },
+
+            _ => println!("Unknown command: \"{}\". Enter 'h' for help", input.trim()),
+        }
+    }
+}
+
+// Turns two words into a count of cows and bulls, making sure they fit into the code length
+fn parse_answer(config: &GameConfig, cows: &str, bulls: &str) -> Result<(u8, u8), String> {
+    match (cows.parse::<u8>(), bulls.parse::<u8>()) {
+        (Ok(cows), Ok(bulls)) if cows as usize + bulls as usize <= config.length() => Ok((cows, bulls)),
+        (Ok(_), Ok(_)) => Err(format!("There can't be more than {} cows and bulls together", config.length())),
+        _ => Err("Enter two numbers: cows and bulls".to_string()),
+    }
+}
+
+// Tells the human which answers could be wrong. We blame the earliest one, because the guesses
+// after it were chosen using the wrong answer already.
+fn explain_mistake(config: &GameConfig, turns: &[Turn]) {
+    let suspects = find_mistakes(config, turns).unwrap_or_default();
+
+    match suspects.first() {
+        Some(&first) => {
+            println!("No number is consistent with your answers, you made a mistake at guess {}", first + 1);
+            if suspects.len() > 1 {
+                let others = suspects[1..].iter()
+                    .map(|index| (index + 1).to_string())
+                    .collect::<Vec<String>>();
+                println!("It could also be guess {}", others.join(", "));
+            }
+        },
+        None => println!("No number is consistent with your answers, more than one of them is wrong"),
+    }
+
+    for (i, turn) in turns.iter().enumerate() {
+        println!("{:>3}: {} - {} cows and {} bulls", i + 1, config.format_code(&turn.guess), turn.cows, turn.bulls);
+    }
+    println!("Use \"fix <guess> <cows> <bulls>\" to correct an answer");
+}
+
+// Prints how many numbers are still possible and lists them if there are not too many
+fn print_candidates(config: &GameConfig, candidates: &[Vec<u8>], limit: usize) {
+    match candidates.len() {
+        0 => println!("No number is possible"),
+        1 => println!("Your number can only be {}", config.format_code(&candidates[0])),
+        count if count <= limit => println!("Your number is one of {}: {}", count,
+            candidates.iter().map(|code| config.format_code(code)).collect::<Vec<String>>().join(" ")),
+        count => println!("{} numbers are still possible", count),
+    }
+}
+
+// The list of commands of the reverse game
+fn print_help() {
+    println!("<C> <B>            - Score my guess with C cows and B bulls");
+    println!("f, fix <N> <C> <B> - Change your answer to guess N");
+    println!("l, list            - List every number that is still possible");
+    println!("q, quit, exit      - Quit game");
+    println!("h, help, ?         - This text");
+}
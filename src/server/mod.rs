@@ -0,0 +1,451 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// This module hosts games over the network, so a whole office can play on one server. Every
+// connection gets its own session with its own secret number, tries and hints. People connect
+// with a plain TCP client like nc and play with text commands, and bots can use the JSON lines
+// protocol instead (see the protocol module). In the duel mode the server pairs the players up
+// as they connect, and every pair plays a duel (see the duel module). In the race mode everybody
+// guesses the same number (see the race module).
+//
+// Every connection is served by its own thread. Threads are the simplest way to do many things at
+// once: each one runs an ordinary loop that reads a line and answers it, and the operating system
+// switches between them. That's fine for an office, but it would be too heavy for thousands of
+// players, so the number of sessions is capped. Every line is read with protocol::read_line(),
+// which refuses lines longer than protocol::MAX_LINE, so one client can't eat up the memory of
+// the whole server.
+
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cowbull::game::{GameConfig, Guess};
+
+use duel::{self, Table};
+use protocol;
+use race::{self, Race};
+use protocol::json::Json;
+use session::Session;
+
+// How often we look at a waiting duel player who has typed something before the duel
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Everything a connection needs to start its session. Options of the main program can't be sent
+// to another thread, because a strategy in a box could be anything, so we copy what we need here.
+#[derive(Clone)]
+pub struct Settings {
+    pub config: GameConfig,
+    pub seed: Option<u64>,
+    pub hard: bool,
+    pub evil: bool,
+    // When true, connections talk the JSON lines protocol instead of text
+    pub json: bool,
+    // When true, connections are paired up to play duels against each other
+    pub duel: bool,
+    // When true, everybody who connects races to guess the same number
+    pub race: bool,
+    // How many sessions can be played at once
+    pub max_sessions: usize,
+    // A session that doesn't send anything for this long is closed
+    pub idle: Duration,
+}
+
+// Accepts connections forever. Only a failure to listen on the address stops the server.
+pub fn run(address: &str, settings: Settings) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    println!("Listening on {}, up to {} sessions", listener.local_addr()?, settings.max_sessions);
+
+    // The number of sessions being played. Every thread changes it, so it has to be shared:
+    // Arc lets many threads own the same value, and an atomic integer can be changed by all of
+    // them without a lock.
+    let active = Arc::new(AtomicUsize::new(0));
+    let mut count = 0;
+
+    // In the duel mode a player waits here until somebody else connects. A thread watches the
+    // waiting player and takes them away if they hang up or wait for too long, so it's shared.
+    let waiting: Arc<Mutex<Option<Waiting>>> = Arc::new(Mutex::new(None));
+
+    // In the race mode this is the race new players join. It's shared with the threads of its
+    // players, and it's replaced by a new one as soon as somebody wins it.
+    let mut race: Option<Arc<Mutex<Race>>> = None;
+
+    for stream in listener.incoming() {
+        // A failed connection is the client's problem, the server goes on
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                println!("Can't accept a connection: {}", error);
+                continue;
+            },
+        };
+
+        // Take a place for the new session, or turn the client away if there's none left
+        if active.fetch_add(1, Ordering::SeqCst) >= settings.max_sessions {
+            active.fetch_sub(1, Ordering::SeqCst);
+            let _ = notice(&mut BufWriter::new(stream), settings.json, "server_full", "The server is full, try again later");
+            continue;
+        }
+        let place = Place(active.clone());
+
+        count += 1;
+        let number = count;
+        let settings = settings.clone();
+
+        if settings.duel {
+            // The take() method leaves None in place of the waiting player. The watcher may not
+            // have noticed yet that the player has hung up, so we check once more. A player who
+            // is gone is dropped here, and that gives their place back.
+            let first = lock(&waiting).take().filter(|first| alive(&first.stream));
+            match first {
+                None => {
+                    let mut output = BufWriter::new(&stream);
+                    let _ = writeln!(output, "Waiting for an opponent...").and_then(|_| output.flush());
+                    drop(output);
+                    match stream.try_clone() {
+                        Ok(watched) => {
+                            *lock(&waiting) = Some(Waiting { number, stream, place });
+                            let waiting = waiting.clone();
+                            thread::spawn(move || watch(&waiting, number, watched, &settings));
+                        },
+                        Err(error) => println!("Can't watch player {}: {}", number, error),
+                    }
+                },
+                Some(first) => {
+                    thread::spawn(move || {
+                        println!("Duel {} started for {} and {}", number, peer(&first.stream), peer(&stream));
+                        match serve_duel(first.stream, stream, &settings) {
+                            Ok(()) => println!("Duel {} ended", number),
+                            Err(error) => println!("Duel {} ended: {}", number, error),
+                        }
+                        drop((first.place, place));
+                    });
+                },
+            }
+            continue;
+        }
+
+        if settings.race {
+            let current = match race {
+                Some(ref current) if !race::lock(current).is_won() => current.clone(),
+                _ => {
+                    let current = Arc::new(Mutex::new(Race::new(settings.config.clone(), settings.seed, settings.hard)));
+                    race = Some(current.clone());
+                    current
+                },
+            };
+            thread::spawn(move || {
+                println!("Racer {} joined from {}", number, peer(&stream));
+                match serve_race(stream, &current, &settings) {
+                    Ok(()) => println!("Racer {} left", number),
+                    Err(error) => println!("Racer {} left: {}", number, error),
+                }
+                drop(place);
+            });
+            continue;
+        }
+
+        // The move keyword gives the closure its own copies of everything it uses, so the thread
+        // doesn't borrow anything from this loop
+        thread::spawn(move || {
+            println!("Session {} started for {}", number, peer(&stream));
+            match serve(stream, &settings) {
+                Ok(()) => println!("Session {} ended", number),
+                Err(error) => println!("Session {} ended: {}", number, error),
+            }
+
+            // The place is given back here. If the session panics, it's given back all the same,
+            // because the closure drops everything it owns on the way out.
+            drop(place);
+        });
+    }
+
+    Ok(())
+}
+
+// The address of the other end of a connection, for the log
+fn peer(stream: &TcpStream) -> String {
+    stream.peer_addr().map(|address| address.to_string()).unwrap_or_else(|_| "unknown".to_string())
+}
+
+// A taken place for a session. The place is given back when the value is dropped, which happens
+// whatever way the thread ends.
+struct Place(Arc<AtomicUsize>);
+
+impl Drop for Place {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// A duel player waiting for an opponent. The number of the connection tells the watcher if the
+// waiting player is still the one it watches.
+struct Waiting {
+    number: usize,
+    stream: TcpStream,
+    place: Place,
+}
+
+// Locks the waiting player. Like with a race, there's nothing a panic could leave broken halfway.
+fn lock(waiting: &Mutex<Option<Waiting>>) -> MutexGuard<'_, Option<Waiting>> {
+    waiting.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Tells if the other end of a connection is still there. We peek without waiting: a connection
+// that was closed has nothing more to read, and a live one either has nothing yet or has a line
+// for us. The line stays where it is, so the duel still gets it.
+fn alive(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return false;
+    }
+    let result = stream.peek(&mut [0; 1]);
+    if stream.set_nonblocking(false).is_err() {
+        return false;
+    }
+    match result {
+        Ok(0) => false,
+        Ok(_) => true,
+        Err(ref error) => error.kind() == io::ErrorKind::WouldBlock,
+    }
+}
+
+// Watches a duel player waiting for an opponent, the way the idle timeout watches everybody else.
+// The player is sent away when the wait is longer than the timeout, and forgotten as soon as they
+// hang up. We only peek at the connection, so anything the player types is left for the duel.
+fn watch(waiting: &Mutex<Option<Waiting>>, number: usize, stream: TcpStream, settings: &Settings) {
+    let deadline = Instant::now() + settings.idle;
+    let ours = |waiting: &Option<Waiting>| waiting.as_ref().is_some_and(|waiting| waiting.number == number);
+    let idle = loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        {
+            // The read timeout is shared by every handle of the connection, so we only change it
+            // while the player is still waiting. Once they're paired, the duel owns it.
+            let guard = lock(waiting);
+            if !ours(&guard) {
+                return;
+            }
+            if left == Duration::ZERO {
+                break true;
+            }
+            if stream.set_read_timeout(Some(left)).is_err() {
+                break false;
+            }
+        }
+
+        match stream.peek(&mut [0; 1]) {
+            // The player has hung up
+            Ok(0) => break false,
+            // Something was typed, we look again in a moment
+            Ok(_) => thread::sleep(left.min(WATCH_INTERVAL)),
+            // Nothing was typed in time, the loop checks the deadline
+            Err(ref error) if error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut => (),
+            Err(_) => break false,
+        }
+    };
+
+    // The player may have been paired while we were looking. Dropping the waiting player closes
+    // the connection and gives the place back.
+    let mut guard = lock(waiting);
+    if ours(&guard) {
+        let gone = guard.take();
+        drop(guard);
+        if idle {
+            let _ = notice(&mut BufWriter::new(&stream), settings.json, "idle", "Nobody came in time, bye");
+            println!("Player {} gave up waiting for an opponent", number);
+        } else {
+            println!("Player {} left while waiting for an opponent", number);
+        }
+        drop(gone);
+    }
+}
+
+// Tells a client why we're hanging up on it. Bots get an error response with a code, just like
+// the ones of the protocol module.
+fn notice<W: Write>(output: &mut W, json: bool, code: &str, message: &str) -> io::Result<()> {
+    if json {
+        let response = Json::Object(vec![
+            ("ok".to_string(), false.into()),
+            ("error".to_string(), code.into()),
+            ("message".to_string(), message.into()),
+        ]);
+        writeln!(output, "{}", response)?;
+    } else {
+        writeln!(output, "{}", message)?;
+    }
+    output.flush()
+}
+
+// Plays one session over a connection
+fn serve(stream: TcpStream, settings: &Settings) -> io::Result<()> {
+    // A read that waits longer than this fails with an error, which ends the session
+    stream.set_read_timeout(Some(settings.idle))?;
+
+    // We need to read and write the same stream at once, so we make a second handle to it. Both
+    // are buffered, otherwise every little piece of an answer would be sent on its own.
+    let input = BufReader::new(stream.try_clone()?);
+    let mut output = BufWriter::new(stream);
+
+    // The rules were checked when the server started, but it costs nothing to be careful
+    let mut session = match Session::new(settings.config.clone(), settings.seed, settings.hard, settings.evil) {
+        Ok(session) => session,
+        Err(error) => return notice(&mut output, settings.json, "bad_rules", &error.to_string()),
+    };
+
+    let result = if settings.json {
+        protocol::serve(&mut session, input, &mut output)
+    } else {
+        play(&mut session, input, &mut output)
+    };
+
+    // Let the client know why we're hanging up on it. Depending on the system, a read that has
+    // timed out fails with one of two kinds of errors. The protocol tells bots about long lines
+    // itself.
+    match result {
+        Err(ref error) if error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut => {
+            let _ = notice(&mut output, settings.json, "idle", "Idle for too long, bye");
+            Err(io::Error::new(io::ErrorKind::TimedOut, "idle for too long"))
+        },
+        Err(error) if error.kind() == io::ErrorKind::InvalidData && !settings.json => {
+            let _ = notice(&mut output, false, "too_long", "The line is too long, bye");
+            Err(error)
+        },
+        result => result,
+    }
+}
+
+// Plays a duel between two connections
+fn serve_duel(first: TcpStream, second: TcpStream, settings: &Settings) -> io::Result<()> {
+    let mut players = Vec::new();
+    for stream in [first, second].iter() {
+        stream.set_read_timeout(Some(settings.idle))?;
+        players.push((BufReader::new(stream.try_clone()?), BufWriter::new(stream.try_clone()?)));
+    }
+    let mut table = duel::Remote { players };
+
+    // The players can't see each other, so they need to know who is who
+    for player in 0..2 {
+        table.tell(player, &format!("You are {}", duel::name(player)))?;
+    }
+
+    match duel::play(&mut table, &settings.config, settings.hard) {
+        Err(ref error) if error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut => {
+            let _ = table.announce("Idle for too long, the duel is over");
+            Err(io::Error::new(io::ErrorKind::TimedOut, "idle for too long"))
+        },
+        Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+            let _ = table.announce("A line was too long, the duel is over");
+            Err(error)
+        },
+        result => result,
+    }
+}
+
+// Plays one player of a race. The race sends its news through a handle of its own.
+fn serve_race(stream: TcpStream, race: &Mutex<Race>, settings: &Settings) -> io::Result<()> {
+    stream.set_read_timeout(Some(settings.idle))?;
+    let input = BufReader::new(stream.try_clone()?);
+    let news = Box::new(stream.try_clone()?);
+    let mut output = BufWriter::new(stream);
+
+    match race::play(race, input, &mut output, news) {
+        Err(ref error) if error.kind() == io::ErrorKind::WouldBlock || error.kind() == io::ErrorKind::TimedOut => {
+            let _ = notice(&mut output, false, "idle", "Idle for too long, bye");
+            Err(io::Error::new(io::ErrorKind::TimedOut, "idle for too long"))
+        },
+        Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+            let _ = notice(&mut output, false, "too_long", "The line is too long, bye");
+            Err(error)
+        },
+        result => result,
+    }
+}
+
+// Plays with text commands, much like the main loop of the program does with the terminal. The
+// prompt is left out, because most network clients show it in a strange place.
+fn play<R: BufRead, W: Write>(session: &mut Session, mut input: R, output: &mut W) -> io::Result<()> {
+    intro(session, output)?;
+
+    while let Some(line) = protocol::read_line(&mut input)? {
+        let input = line.trim();
+
+        match input {
+            "" => continue,
+            "q" | "quit" | "exit" => {
+                session.finish();
+                writeln!(output, "Bye")?;
+                return output.flush();
+            },
+            "h" | "help" | "?" => {
+                writeln!(output, "r, restart    - Give up and start a new game")?;
+                writeln!(output, "q, quit, exit - Leave the server")?;
+                writeln!(output, "h, help, ?    - This text")?;
+                writeln!(output, "s, hints      - Check out hints on symbol positions")?;
+                writeln!(output, "stats         - Show how your games on the server went")?;
+                writeln!(output, "history       - List your guesses with their answers")?;
+                writeln!(output, "<guess>       - Enter {} to guess the number", session.game.config.describe())?;
+            },
+            "s" | "hints" => for line in ::hint_lines(&session.game) {
+                writeln!(output, "{}", line)?;
+            },
+            // The same summary of the session the terminal shows
+            "stats" => if session.played() > 0 {
+                for line in session.summary_lines() {
+                    writeln!(output, "{}", line)?;
+                }
+            } else {
+                writeln!(output, "No games played yet")?;
+            },
+            "history" => for line in ::history_lines(&session.game) {
+                writeln!(output, "{}", line)?;
+            },
+            "r" | "restart" => {
+                session.restart();
+                intro(session, output)?;
+            },
+            _ => match Guess::parse(input, &session.game.config) {
+                Err(error) => writeln!(output, "{}", error)?,
+                Ok(guess) => {
+                    if let Err(contradiction) = session.game.check_hard(&guess) {
+                        writeln!(output, "{}", contradiction)?;
+                    } else {
+                        let (cows, bulls, won) = session.game.play(&guess);
+                        if won {
+                            let tries = session.game.tries;
+                            writeln!(output, "You won in {} tries!", tries)?;
+                            session.win();
+                            intro(session, output)?;
+                        } else {
+                            session.game.analyze();
+                            if cows == 0 && bulls == 0 {
+                                writeln!(output, "Nothing found")?;
+                            } else {
+                                writeln!(output, "Found {} cows and {} bulls", cows, bulls)?;
+                            }
+                        }
+                    }
+                },
+            },
+        }
+        output.flush()?;
+    }
+
+    // The client has hung up
+    session.finish();
+    Ok(())
+}
+
+// Invites the player to a new game
+fn intro<W: Write>(session: &Session, output: &mut W) -> io::Result<()> {
+    writeln!(output, "Guess the number of {}! (Enter 'q' to quit, 'h' for help)", session.game.config.describe())?;
+    if session.game.hard {
+        writeln!(output, "Hard mode: every guess must agree with all earlier answers")?;
+    }
+    if session.game.is_evil() {
+        writeln!(output, "Evil mode: I won't pick my number until you leave me no choice")?;
+    }
+    output.flush()
+}
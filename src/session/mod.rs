@@ -0,0 +1,253 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// This module keeps track of a playing session: everything that happens from the start of the
+// program until the player quits. A session owns the current game and replaces it with a new one
+// when the player restarts or wins, so the program can run for days without piling anything up.
+// It also counts the games, so we can tell the player how the session went at the end, and it can
+// record every finished game in the stats file and write its transcript, so the results outlive
+// the program.
+
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+
+use cowbull::game::{Game, GameConfig};
+use cowbull::leaderboard::{self, Entry, Leaderboard, Spoiler};
+use cowbull::solver::SolverError;
+use cowbull::stats::{Outcome, Record, Stats};
+use cowbull::transcript::{self, Transcript};
+
+pub struct Session {
+    // The rules, the seed and the mode of every new game of the session
+    config: GameConfig,
+    seed: Option<u64>,
+    hard: bool,
+    evil: bool,
+
+    // The game that is being played right now. It's public, so the main loop can play it.
+    pub game: Game,
+
+    // How many games were played, won or not. A game without a single guess doesn't count.
+    played: u32,
+
+    // The number of tries of every won game
+    wins: Vec<u32>,
+
+    // The last won game, so the player can look back at it after a new one has started
+    last: Option<Game>,
+
+    // The name of the player's profile, or None if the player hasn't picked one. The player's
+    // games are saved and recorded in the player's own folder.
+    player: Option<String>,
+
+    // The stats file finished games are recorded to, if any. Only the games of the player at the
+    // terminal are recorded, not the ones of bots or people on the server.
+    stats: Option<PathBuf>,
+
+    // The leaderboard file won seeded games are entered to, if any
+    leaderboard: Option<PathBuf>,
+
+    // The folder the transcripts of finished games are written to, if any
+    transcripts: Option<PathBuf>,
+
+    // The last error of writing the stats, the leaderboard or a transcript, until the frontend
+    // asks for it
+    record_error: Option<io::Error>,
+}
+
+impl Session {
+
+    // Starts a session with the first game. The evil host can't play with too many possible
+    // numbers, and it's better to find that out right away.
+    pub fn new(config: GameConfig, seed: Option<u64>, hard: bool, evil: bool) -> Result<Session, SolverError> {
+        let game = if evil {
+            Game::evil(config.clone())?
+        } else {
+            Game::new(config.clone(), seed)
+        };
+
+        let mut session = Session {
+            game,
+            config,
+            seed,
+            hard,
+            evil,
+            played: 0,
+            wins: Vec::new(),
+            last: None,
+            player: None,
+            stats: None,
+            leaderboard: None,
+            transcripts: None,
+            record_error: None,
+        };
+        session.game.hard = hard;
+        Ok(session)
+    }
+
+    // The name of the player's profile, if any
+    pub fn player(&self) -> Option<&str> {
+        self.player.as_deref()
+    }
+
+    // Tells whose games these are
+    pub fn set_player(&mut self, player: Option<String>) {
+        self.player = player;
+    }
+
+    // Starts recording every finished game to a stats file
+    pub fn keep_stats(&mut self, path: PathBuf) {
+        self.stats = Some(path);
+    }
+
+    // Starts entering every won seeded game to a leaderboard file
+    pub fn keep_leaderboard(&mut self, path: PathBuf) {
+        self.leaderboard = Some(path);
+    }
+
+    // Starts writing a transcript of every finished game to a folder
+    pub fn keep_transcripts(&mut self, dir: PathBuf) {
+        self.transcripts = Some(dir);
+    }
+
+    // Returns the error of the last failed record, if there was one, and forgets it. The session
+    // can't tell the player itself, because it doesn't know where the player is looking.
+    pub fn record_error(&mut self) -> Option<io::Error> {
+        self.record_error.take()
+    }
+
+    // Gives up the current game and starts a new one with the same rules
+    pub fn restart(&mut self) {
+        self.finish();
+        self.new_game();
+    }
+
+    // Records that the current game was won and starts a new one. The winning guess must be
+    // scored first, so it's in the history: the tries count it, and the leaderboard checks a
+    // seeded game by playing every guess again.
+    pub fn win(&mut self) {
+        self.played += 1;
+        self.wins.push(self.game.tries);
+        self.record(Outcome::Won);
+
+        let player = self.player.clone().unwrap_or_else(|| leaderboard::ANONYMOUS.to_string());
+        if let (Some(ref path), Some(entry)) = (&self.leaderboard, Entry::from_game(&self.game, &player)) {
+            if let Err(error) = Leaderboard::append_file(path, &entry) {
+                self.record_error = Some(error);
+            }
+        }
+
+        self.last = Some(self.new_game());
+    }
+
+    // Records that the computer has finished the current game and starts a new one. It's not the
+    // player's win, but the game was played.
+    pub fn solved(&mut self) {
+        self.played += 1;
+        self.record(Outcome::Solved);
+        self.spoil();
+        self.new_game();
+    }
+
+    // The last won game, if there was one
+    pub fn last_game(&self) -> Option<&Game> {
+        self.last.as_ref()
+    }
+
+    // Replaces the current game with a new one and returns the old one
+    fn new_game(&mut self) -> Game {
+        let mut game = if self.evil {
+            // The first game of the session was made with the same rules, so this can't fail
+            Game::evil(self.config.clone()).expect("the rules were checked by the first game")
+        } else {
+            Game::new(self.config.clone(), self.seed)
+        };
+        game.hard = self.hard;
+
+        // The replace() function puts the new game in place of the old one and gives the old one
+        // back to us
+        mem::replace(&mut self.game, game)
+    }
+
+    // Counts the current game as played if there was at least one guess. We call it when the game
+    // is abandoned: on restart and when the player quits without saving it.
+    pub fn finish(&mut self) {
+        if self.game.tries > 0 {
+            self.played += 1;
+            self.record(Outcome::GaveUp);
+            self.spoil();
+        }
+    }
+
+    // Records a saved game of an earlier run as given up, because the player has started another
+    // one instead of resuming it. It's not a game of this session, so it isn't counted. For a
+    // moment the saved game takes the place of the current one, so it's recorded the same way.
+    pub fn abandon(&mut self, saved: Game) {
+        let current = mem::replace(&mut self.game, saved);
+        self.record(Outcome::GaveUp);
+        self.spoil();
+        self.game = current;
+    }
+
+    // Tells the leaderboard that the player has seen the number of the current game without
+    // winning it. The next game of a seeded session has the same number, and it would be an easy
+    // win otherwise. We call it when the game is given up or solved by the computer, and the main
+    // loop calls it when the player exports the transcript of a game in progress.
+    pub fn spoil(&mut self) {
+        let player = self.player.clone().unwrap_or_else(|| leaderboard::ANONYMOUS.to_string());
+        if let (Some(ref path), Some(spoiler)) = (&self.leaderboard, Spoiler::from_game(&self.game, &player)) {
+            if let Err(error) = Leaderboard::append_spoiler(path, &spoiler) {
+                self.record_error = Some(error);
+            }
+        }
+    }
+
+    // Records the current game in the stats file and writes its transcript, if the session keeps
+    // them
+    fn record(&mut self, outcome: Outcome) {
+        if let Some(ref path) = self.stats {
+            if let Err(error) = Stats::append_file(path, &Record::new(&self.game, outcome)) {
+                self.record_error = Some(error);
+            }
+        }
+        if let Some(ref dir) = self.transcripts {
+            if let Err(error) = transcript::save_to_dir(dir, &Transcript::from_game(&self.game, Some(outcome))) {
+                self.record_error = Some(error);
+            }
+        }
+    }
+
+    // How many games were played in this session
+    pub fn played(&self) -> u32 {
+        self.played
+    }
+
+    // Tells how the session went, line by line, so the server can send the same lines to its
+    // players. There's nothing to tell if no game was played.
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.played == 0 {
+            return lines;
+        }
+
+        lines.push(format!("Games played: {}, won: {}", self.played, self.wins.len()));
+
+        // The iterator methods sum() and min() need to know the type, and min() returns None for
+        // an empty iterator, so the if let skips the line if nothing was won
+        if let Some(best) = self.wins.iter().min() {
+            let total = self.wins.iter().sum::<u32>();
+            lines.push(format!("Average tries to win: {:.1}, best: {}", total as f32 / self.wins.len() as f32, best));
+        }
+        lines
+    }
+
+    // Prints how the session went
+    pub fn print_summary(&self) {
+        for line in self.summary_lines() {
+            println!("{}", line);
+        }
+    }
+}
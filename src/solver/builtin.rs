@@ -0,0 +1,137 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! The strategies that come with the game. All of them but the first one rate every guess by how
+//! it splits the candidates into groups of codes that would give the same answer, and play the
+//! guess with the best rating.
+
+use game::{GameConfig, Turn};
+
+use super::{Strategy, partition};
+
+// Smart strategies score every possible guess against every candidate. When there are too many
+// of them, we only consider guesses from the candidates themselves, and if that's still too much,
+// only some of the candidates. This keeps every move within a few seconds.
+const MAX_WORK: usize = 10_000_000;
+
+/// The well-known ways to choose the next guess
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Builtin {
+    /// Just play the first code that is still possible. Fast, but not clever.
+    First,
+    /// Donald Knuth's idea: play the code whose worst answer leaves the fewest candidates
+    Minimax,
+    /// Play the code whose answer tells us the most, measured in bits of information
+    Entropy,
+    /// Play the code that leaves the fewest candidates on average
+    ExpectedSize,
+}
+
+impl Builtin {
+
+    /// Finds a strategy by a name the user typed. Besides the names of the strategies, it knows
+    /// a few other ones people use. Returns None for unknown names.
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        match name {
+            "first" => Some(Builtin::First),
+            "minimax" | "knuth" => Some(Builtin::Minimax),
+            "entropy" => Some(Builtin::Entropy),
+            "expected" | "expected-size" => Some(Builtin::ExpectedSize),
+            _ => None,
+        }
+    }
+
+    // Splits the candidates into groups by the answer they would give to the guess, and rates
+    // these groups according to our strategy. Lower is better for all of them.
+    fn cost(&self, config: &GameConfig, candidates: &[Vec<u8>], guess: &[u8]) -> f64 {
+        let sizes = partition(config, candidates, guess);
+        let total = candidates.len() as f64;
+
+        match *self {
+            // Not used, the first candidate is picked without rating anything
+            Builtin::First => 0.0,
+
+            // The size of the largest group is the number of candidates we'll have left in the
+            // worst case
+            Builtin::Minimax => sizes.iter().cloned().max().unwrap_or(0) as f64,
+
+            // Entropy is the sum of -p * log2(p) over all groups, where p is the chance to get
+            // this answer. We want the largest entropy, so the cost is negative.
+            Builtin::Entropy => sizes.iter()
+                .filter(|&&size| size > 0)
+                .map(|&size| {
+                    let p = size as f64 / total;
+                    p * p.log2()
+                })
+                .sum(),
+
+            // If the secret is in a group of size n, we'll have n candidates left. The chance of
+            // that is n / total, so on average we keep the sum of n * n / total candidates.
+            Builtin::ExpectedSize => sizes.iter()
+                .map(|&size| (size * size) as f64 / total)
+                .sum(),
+        }
+    }
+}
+
+impl Strategy for Builtin {
+
+    fn name(&self) -> &str {
+        match *self {
+            Builtin::First => "first",
+            Builtin::Minimax => "minimax",
+            Builtin::Entropy => "entropy",
+            Builtin::ExpectedSize => "expected-size",
+        }
+    }
+
+    // The candidates already tell everything we know, so we only look at the history to see if
+    // this is the first guess
+    fn next_guess(&self, config: &GameConfig, history: &[Turn], space: &[Vec<u8>], candidates: &[Vec<u8>]) -> Option<Vec<u8>> {
+        // There's nothing to think about when we have one candidate or none at all
+        if candidates.len() <= 1 || *self == Builtin::First {
+            return candidates.first().cloned();
+        }
+
+        // Pick the codes to consider as guesses. Before the first answer two codes are equally
+        // good if one turns into the other by renaming symbols and moving positions, so we only
+        // check one code of each shape, like 0000, 0001, 0011 and 0012. With unique symbols
+        // there's just one shape: 0123. Later, the more candidates we have, the fewer guesses we
+        // can afford to check.
+        let pool: Vec<&Vec<u8>> = if history.is_empty() {
+            space.iter().filter(|code| first_of_shape(code)).collect()
+        } else if space.len() * candidates.len() <= MAX_WORK {
+            space.iter().collect()
+        } else {
+            let step = (candidates.len() * candidates.len() / MAX_WORK).max(1);
+            candidates.iter().step_by(step).collect()
+        };
+
+        // Find the guess with the lowest cost. A guess that can be the secret number wins ties,
+        // because it could end the game right away.
+        let mut best: Option<(f64, bool, &Vec<u8>)> = None;
+        for guess in pool {
+            let cost = self.cost(config, candidates, guess);
+            let possible = candidates.contains(guess);
+            let better = match best {
+                None => true,
+                Some((best_cost, best_possible, _)) =>
+                    cost < best_cost || (cost == best_cost && possible && !best_possible),
+            };
+            if better {
+                best = Some((cost, possible, guess));
+            }
+        }
+
+        best.map(|(_, _, guess)| guess.clone())
+    }
+}
+
+// Tells if the code is the first one of its shape: it starts with symbol 0 and every next symbol
+// is either the same as the one before it or the next one. Every code can be turned into such
+// a code by renaming symbols and moving positions.
+fn first_of_shape(code: &[u8]) -> bool {
+    code.first() == Some(&0) && code.windows(2).all(|pair| pair[1] == pair[0] || pair[1] == pair[0] + 1)
+}
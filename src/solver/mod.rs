@@ -0,0 +1,299 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module is a computer player. It doesn't peek at the secret number, instead it keeps a list
+//! of every code that is still possible (we call them candidates) and throws away the ones that
+//! contradict the answers of the game. What's left is exactly what the player could know with a
+//! perfect memory. The only hard question is which code to try next, and there are a few
+//! well-known answers to that question, which we call strategies. A strategy is anything that
+//! implements the Strategy trait, so bots can bring their own.
+
+use std::fmt;
+use std::time::SystemTime;
+
+// We need the game rules, the scoring function and the game itself. Since we are not inside the
+// game module, we use the path from the root of our crate.
+use game::{Game, GameConfig, Turn, score};
+
+// The strategies that come with the game live in their own file
+pub mod builtin;
+pub use self::builtin::Builtin;
+
+/// We refuse to enumerate more codes than this. A million codes of ten bytes each is already
+/// a lot of memory, and 26 letters on 10 positions would never fit.
+pub const MAX_SPACE: u64 = 1_000_000;
+
+/// A way to choose the next guess. A trait is a set of methods a type promises to have, so the
+/// solver can work with any strategy without knowing what it is.
+///
+/// The tournament assumes that a strategy always makes the same guess for the same history.
+pub trait Strategy {
+    /// The name we show to the user and find the strategy by
+    fn name(&self) -> &str;
+
+    /// Chooses the next guess. The history holds every guess made so far with its answer, the
+    /// space holds every code the rules allow, and the candidates are the codes that agree with
+    /// the history. Returns None if there's nothing to guess, which means somebody has made
+    /// a mistake while scoring.
+    fn next_guess(&self, config: &GameConfig, history: &[Turn], space: &[Vec<u8>], candidates: &[Vec<u8>]) -> Option<Vec<u8>>;
+}
+
+/// Every strategy we know about. The solve command, the reverse game and the tournament find
+/// strategies here, so a new strategy only has to be added to this list.
+pub fn strategies() -> Vec<Box<dyn Strategy>> {
+    vec![
+        Box::new(Builtin::First),
+        Box::new(Builtin::Minimax),
+        Box::new(Builtin::Entropy),
+        Box::new(Builtin::ExpectedSize),
+    ]
+}
+
+/// Finds a strategy by name. The builtin strategies also answer to a few other names people use,
+/// like knuth for minimax.
+pub fn find_strategy(name: &str) -> Option<Box<dyn Strategy>> {
+    let name = Builtin::from_name(name).map(|builtin| builtin.name().to_string()).unwrap_or_else(|| name.to_string());
+    strategies().into_iter().find(|strategy| strategy.name() == name)
+}
+
+/// The names of every strategy, for messages like "try first, minimax or entropy"
+pub fn strategy_names() -> String {
+    let names = strategies().iter().map(|strategy| strategy.name().to_string()).collect::<Vec<String>>();
+    match names.split_last() {
+        Some((last, others)) if !others.is_empty() => format!("{} or {}", others.join(", "), last),
+        _ => names.join(""),
+    }
+}
+
+/// The solver can't work when there are too many possible codes
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum SolverError {
+    /// The number of possible codes
+    SpaceTooLarge(u64),
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SolverError::SpaceTooLarge(size) =>
+                write!(f, "There are {} possible codes, the solver can handle up to {}", size, MAX_SPACE),
+        }
+    }
+}
+
+/// The solver itself. It borrows the strategy, so the same strategy can be used by many solvers.
+/// The 'a is a lifetime: it tells the compiler that the solver can't outlive the strategy.
+pub struct Solver<'a> {
+    // The rules we are playing by
+    config: GameConfig,
+    // How we pick the next guess
+    strategy: &'a dyn Strategy,
+    // Every code these rules allow. Smart strategies may guess a code that can't be the secret,
+    // if its answer tells us more.
+    space: Vec<Vec<u8>>,
+    // Codes that are consistent with every answer we've heard so far
+    candidates: Vec<Vec<u8>>,
+    // Every answer we've heard so far
+    history: Vec<Turn>,
+    // In hard mode only the candidates can be guessed
+    hard: bool,
+}
+
+impl<'a> Solver<'a> {
+
+    /// Creates a solver for a fresh game with the given rules
+    pub fn new(config: &GameConfig, strategy: &'a dyn Strategy) -> Result<Solver<'a>, SolverError> {
+        let space = all_codes(config)?;
+
+        Ok(Solver {
+            config: config.clone(),
+            strategy,
+            candidates: space.clone(),
+            space,
+            history: Vec::new(),
+            hard: false,
+        })
+    }
+
+    /// Creates a solver that continues a game. Every answer from the game history is applied, so
+    /// the solver knows everything the player should know by now.
+    /// The solver follows the rule of hard mode, if the game is played in it.
+    pub fn from_game(game: &Game, strategy: &'a dyn Strategy) -> Result<Solver<'a>, SolverError> {
+        let mut solver = Solver::from_turns(&game.config, &game.history, strategy)?;
+        solver.set_hard(game.hard);
+        Ok(solver)
+    }
+
+    /// Creates a solver and applies a list of answers that were given by somebody else, for
+    /// example by a human who keeps the secret number in their head
+    pub fn from_turns(config: &GameConfig, turns: &[Turn], strategy: &'a dyn Strategy) -> Result<Solver<'a>, SolverError> {
+        let mut solver = Solver::new(config, strategy)?;
+        for turn in turns {
+            solver.apply(turn.clone());
+        }
+        Ok(solver)
+    }
+
+    /// Remembers the answer to a guess. Only the candidates that would give exactly the same
+    /// answer if they were the secret number survive.
+    pub fn record(&mut self, guess: &[u8], cows: u8, bulls: u8) {
+        self.apply(Turn { guess: guess.to_vec(), cows, bulls, time: SystemTime::now() });
+    }
+
+    // Drops the candidates that don't agree with the turn and adds it to the history. The
+    // retain() method drops every element for which the closure returns false.
+    fn apply(&mut self, turn: Turn) {
+        self.candidates.retain(|code| score(code, &turn.guess) == (turn.cows, turn.bulls));
+        self.history.push(turn);
+    }
+
+    /// Turns hard mode on or off. In hard mode the strategy can only guess the candidates.
+    pub fn set_hard(&mut self, hard: bool) {
+        self.hard = hard;
+    }
+
+    /// Codes that could still be the secret number
+    pub fn candidates(&self) -> &[Vec<u8>] {
+        &self.candidates
+    }
+
+    /// The strategy this solver uses
+    pub fn strategy(&self) -> &dyn Strategy {
+        self.strategy
+    }
+
+    /// Asks the strategy for the next guess. Returns None if no code is consistent with the
+    /// answers, which means somebody has made a mistake while scoring.
+    pub fn next_guess(&self) -> Option<Vec<u8>> {
+        if self.candidates.is_empty() {
+            return None;
+        }
+
+        // The space is what the strategy can guess, and in hard mode that's only the candidates
+        let space = if self.hard { &self.candidates } else { &self.space };
+        self.strategy.next_guess(&self.config, &self.history, space, &self.candidates)
+    }
+}
+
+/// When no code fits a list of answers, at least one answer is wrong. This function finds the
+/// answers that are to blame: if we forget one of them and the rest make sense again, it could be
+/// the mistake. Returns the indices of such answers in the list, from the earliest to the latest.
+pub fn find_mistakes(config: &GameConfig, turns: &[Turn]) -> Result<Vec<usize>, SolverError> {
+    let space = all_codes(config)?;
+
+    // The filter() keeps the indices for which the closure returns true. For each index we check
+    // whether any code agrees with every answer except that one.
+    Ok((0..turns.len())
+        .filter(|&skip| space.iter().any(|code| {
+            turns.iter()
+                .enumerate()
+                .all(|(i, turn)| i == skip || score(code, &turn.guess) == (turn.cows, turn.bulls))
+        }))
+        .collect())
+}
+
+/// Counts how many candidates would give every possible answer to the guess. An answer of c cows
+/// and b bulls is stored at index c * (length + 1) + b of the resulting vector.
+pub fn partition(config: &GameConfig, candidates: &[Vec<u8>], guess: &[u8]) -> Vec<usize> {
+    let side = config.length() + 1;
+    let mut sizes = vec![0; side * side];
+    for code in candidates {
+        let (cows, bulls) = score(code, guess);
+        sizes[cows as usize * side + bulls as usize] += 1;
+    }
+    sizes
+}
+
+/// Builds a list of every code the rules allow, in the natural order of symbols: 0123, 0124, and
+/// so on. Returns an error if there are too many of them.
+pub fn all_codes(config: &GameConfig) -> Result<Vec<Vec<u8>>, SolverError> {
+    let size = config.space_size();
+    if size > MAX_SPACE {
+        return Err(SolverError::SpaceTooLarge(size));
+    }
+
+    let mut codes = Vec::with_capacity(size as usize);
+    let mut code = Vec::with_capacity(config.length());
+    extend(config, &mut code, &mut codes);
+    Ok(codes)
+}
+
+// A recursive helper for all_codes(). It tries every unused symbol at the next position (or every
+// symbol at all, if repeats are allowed), and when the code is long enough, saves a copy of it.
+fn extend(config: &GameConfig, code: &mut Vec<u8>, codes: &mut Vec<Vec<u8>>) {
+    if code.len() == config.length() {
+        codes.push(code.clone());
+        return;
+    }
+
+    for symbol in (0..config.symbols().len()).map(|symbol| symbol as u8) {
+        if config.repeats() || !code.contains(&symbol) {
+            code.push(symbol);
+            extend(config, code, codes);
+            code.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Solver, SolverError, all_codes, strategies};
+    use game::{Alphabet, GameConfig, score};
+
+    fn custom(symbols: &str) -> Alphabet {
+        Alphabet::Custom(symbols.chars().collect())
+    }
+
+    // Plays every secret the rules allow with every strategy. Each guess either wins or drops at
+    // least one candidate, so a game that takes longer than there are codes never ends.
+    fn solves_everything(config: &GameConfig) {
+        let space = all_codes(config).unwrap();
+        for strategy in strategies() {
+            for secret in &space {
+                let mut solver = Solver::new(config, &*strategy).unwrap();
+                let mut tries = 0;
+                loop {
+                    let guess = solver.next_guess().expect("the solver gave up");
+                    assert!(config.is_valid_code(&guess));
+                    let (cows, bulls) = score(secret, &guess);
+                    if bulls as usize == config.length() {
+                        break;
+                    }
+                    tries += 1;
+                    assert!(tries < space.len(), "{} never guessed {:?}", strategy.name(), secret);
+                    solver.record(&guess, cows, bulls);
+                    assert!(solver.candidates().contains(secret));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solver_always_finishes() {
+        solves_everything(&GameConfig::new(3, custom("abcde")).unwrap());
+        solves_everything(&GameConfig::with_repeats(3, custom("abc"), true).unwrap());
+    }
+
+    #[test]
+    fn wrong_answers_leave_nothing_to_guess() {
+        let config = GameConfig::new(3, custom("abcde")).unwrap();
+        let strategy = &strategies()[0];
+        let mut solver = Solver::new(&config, &**strategy).unwrap();
+        // Three cows for 012 and zero for 210 can't both be true
+        solver.record(&[0, 1, 2], 3, 0);
+        solver.record(&[2, 1, 0], 0, 0);
+        assert_eq!(solver.next_guess(), None);
+    }
+
+    #[test]
+    fn space() {
+        assert_eq!(all_codes(&GameConfig::default()).unwrap().len(), 5040);
+        let codes = all_codes(&GameConfig::with_repeats(3, custom("ab"), true).unwrap()).unwrap();
+        assert_eq!(codes.len(), 8);
+        assert_eq!(codes[..3], [vec![0, 0, 0], vec![0, 0, 1], vec![0, 1, 0]]);
+        assert_eq!(all_codes(&GameConfig::new(10, Alphabet::Letters).unwrap()).err(),
+                   Some(SolverError::SpaceTooLarge(19_275_223_968_000)));
+    }
+}
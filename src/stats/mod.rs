@@ -0,0 +1,397 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module keeps the statistics of the player across runs: every finished game is recorded,
+//! and the records are turned into games played, win rate, streaks and so on. The records are
+//! kept in a plain text file, one game per line:
+//!
+//! ```text
+//! cowbull-stats 1
+//! game 1435400100 won 5 93 4 digits
+//! game 1435400300 gave-up 3 40 5 hex hard
+//! game 1435400900 solved 7 310 4 custom:abcdef repeats evil
+//! ```
+//!
+//! A line tells when the game was finished (in Unix time), how it ended, how many tries it took,
+//! how long it took in seconds, the rules as `game::format` writes them, and the modes that were
+//! on. Records are only ever added to the end of the file, so a game is never lost because another
+//! one is being written, even by another copy of the program. A line that can't be read, for
+//! example one cut short by a crash, is skipped and the rest of the file is still used.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use game::{Game, GameConfig, from_unix_time, unix_time};
+use game::format::{check_header, parse_rules, rules_words, write_header};
+
+// The first word of a stats file
+const MAGIC: &str = "cowbull-stats";
+
+/// The version of the format we write
+pub const STATS_VERSION: u32 = 1;
+
+/// How many of the latest wins make the rolling average
+pub const ROLLING: usize = 10;
+
+// The longest bar of the histogram of tries
+const BAR: usize = 40;
+
+/// How a game has ended
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Outcome {
+    /// The player has guessed the number
+    Won,
+    /// The player has started a new game or quit before guessing it
+    GaveUp,
+    /// The computer has finished the game for the player
+    Solved,
+}
+
+impl Outcome {
+
+    /// The word for the outcome in files
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Won => "won",
+            Outcome::GaveUp => "gave-up",
+            Outcome::Solved => "solved",
+        }
+    }
+
+    /// Turns the word back into the outcome
+    pub fn from_name(name: &str) -> Option<Outcome> {
+        match name {
+            "won" => Some(Outcome::Won),
+            "gave-up" => Some(Outcome::GaveUp),
+            "solved" => Some(Outcome::Solved),
+            _ => None,
+        }
+    }
+}
+
+/// One finished game
+#[derive(Clone, Debug)]
+pub struct Record {
+    /// The moment the game was finished
+    pub finished: SystemTime,
+    /// How the game has ended
+    pub outcome: Outcome,
+    /// The number of tries, the winning one included
+    pub tries: u32,
+    /// How long the game took
+    pub duration: Duration,
+    /// The rules of the game
+    pub config: GameConfig,
+    /// True if the game was played in hard mode
+    pub hard: bool,
+    /// True if the game was played against the evil host
+    pub evil: bool,
+}
+
+impl Record {
+
+    /// Makes a record of a game that has just ended. A won game must have its winning guess
+    /// scored, so it's counted in the tries.
+    pub fn new(game: &Game, outcome: Outcome) -> Record {
+        let finished = SystemTime::now();
+        Record {
+            finished,
+            outcome,
+            tries: game.tries,
+            // The clock can go backwards, and then the game took no time at all
+            duration: finished.duration_since(game.started).unwrap_or_default(),
+            config: game.config.clone(),
+            hard: game.hard,
+            evil: game.is_evil(),
+        }
+    }
+
+    // Writes the record as a line of the file
+    fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut line = format!("game {} {} {} {} {}", unix_time(self.finished), self.outcome.name(),
+                               self.tries, self.duration.as_secs(), rules_words(&self.config));
+        for &(on, flag) in &[(self.hard, "hard"), (self.evil, "evil")] {
+            if on {
+                line.push(' ');
+                line.push_str(flag);
+            }
+        }
+
+        // The whole line is written at once, so lines of two programs writing at the same time
+        // don't get mixed up
+        out.write_all(format!("{}\n", line).as_bytes())
+    }
+
+    // Reads a record from a line of the file, or None if the line is damaged
+    fn parse(line: &str) -> Option<Record> {
+        let words = line.split_whitespace().collect::<Vec<&str>>();
+        match words.as_slice() {
+            ["game", finished, outcome, tries, seconds, length, alphabet, flags @ ..] => {
+                let config = parse_rules(length, alphabet, flags).ok()?;
+                Some(Record {
+                    finished: from_unix_time(finished.parse().ok()?),
+                    outcome: Outcome::from_name(outcome)?,
+                    tries: tries.parse().ok()?,
+                    duration: Duration::from_secs(seconds.parse().ok()?),
+                    config,
+                    hard: flags.contains(&"hard"),
+                    evil: flags.contains(&"evil"),
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
+/// All the recorded games, from the earliest to the latest
+#[derive(Default)]
+pub struct Stats {
+    /// The records in the order they were written
+    pub records: Vec<Record>,
+    /// How many lines of the file couldn't be read
+    pub skipped: usize,
+}
+
+impl Stats {
+
+    /// Reads the records from anything that can be read line by line
+    pub fn load<R: BufRead>(input: R) -> io::Result<Stats> {
+        let mut stats = Stats::default();
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+
+            // The header tells which version wrote the file. A newer version could write records
+            // we'd misunderstand, so we'd rather not read them at all.
+            if i == 0 {
+                check_header(&line, MAGIC, STATS_VERSION..=STATS_VERSION)
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("bad stats file: {}", error)))?;
+                continue;
+            }
+
+            if line.trim().is_empty() {
+                continue;
+            }
+            match Record::parse(&line) {
+                Some(record) => stats.records.push(record),
+                None => stats.skipped += 1,
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Reads the records from a file. If there's no file yet, nothing was recorded.
+    pub fn load_file(path: &Path) -> io::Result<Stats> {
+        match File::open(path) {
+            Ok(file) => Stats::load(BufReader::new(file)),
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(Stats::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Adds a record to the end of a file, creating the file with its header if needed
+    pub fn append_file(path: &Path, record: &Record) -> io::Result<()> {
+        let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            write_header(&mut file, MAGIC, STATS_VERSION)?;
+        }
+        record.write(&mut file)
+    }
+
+    /// The number of recorded games
+    pub fn played(&self) -> usize {
+        self.records.len()
+    }
+
+    /// The number of won games
+    pub fn won(&self) -> usize {
+        self.wins().count()
+    }
+
+    /// The share of won games from 0 to 1, or None if nothing was played
+    pub fn win_rate(&self) -> Option<f64> {
+        match self.played() {
+            0 => None,
+            played => Some(self.won() as f64 / played as f64),
+        }
+    }
+
+    /// The number of wins in a row at the end of the records
+    pub fn current_streak(&self) -> usize {
+        self.records.iter().rev().take_while(|record| record.outcome == Outcome::Won).count()
+    }
+
+    /// The longest number of wins in a row
+    pub fn best_streak(&self) -> usize {
+        // The fold() walks the records keeping two numbers: the current streak and the best one
+        self.records.iter()
+            .fold((0, 0), |(current, best), record| {
+                let current = if record.outcome == Outcome::Won { current + 1 } else { 0 };
+                (current, best.max(current))
+            })
+            .1
+    }
+
+    /// The average tries of every won game, or None if nothing was won
+    pub fn average_tries(&self) -> Option<f64> {
+        average(self.wins().map(|record| record.tries))
+    }
+
+    /// The average tries of the latest wins, see ROLLING. It shows if the player is getting better.
+    pub fn rolling_average(&self) -> Option<f64> {
+        let tries = self.wins().map(|record| record.tries).collect::<Vec<u32>>();
+        average(tries[tries.len().saturating_sub(ROLLING)..].iter().cloned())
+    }
+
+    /// The average time of a won game, or None if nothing was won
+    pub fn average_time(&self) -> Option<Duration> {
+        average(self.wins().map(|record| record.duration.as_secs() as u32)).map(Duration::from_secs_f64)
+    }
+
+    /// How many games were won with every number of tries, from the fewest tries to the most.
+    /// Numbers of tries nobody has won with are left out.
+    pub fn histogram(&self) -> Vec<(u32, usize)> {
+        let mut tries = self.wins().map(|record| record.tries).collect::<Vec<u32>>();
+        tries.sort();
+
+        let mut histogram: Vec<(u32, usize)> = Vec::new();
+        for count in tries {
+            match histogram.last_mut() {
+                Some(&mut (last, ref mut games)) if last == count => *games += 1,
+                _ => histogram.push((count, 1)),
+            }
+        }
+        histogram
+    }
+
+    /// Writes the statistics as text for the terminal
+    pub fn write_text<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let rate = match self.win_rate() {
+            Some(rate) => rate,
+            None => return writeln!(out, "No games recorded yet"),
+        };
+
+        let count = |outcome| self.records.iter().filter(|record| record.outcome == outcome).count();
+        writeln!(out, "Games played: {}, won: {} ({:.0}%), given up: {}, solved by the computer: {}",
+                 self.played(), self.won(), rate * 100.0, count(Outcome::GaveUp), count(Outcome::Solved))?;
+        writeln!(out, "Current streak: {}, best streak: {}", self.current_streak(), self.best_streak())?;
+
+        // Everything below is about won games, and there may be none
+        if let (Some(all), Some(latest), Some(time)) = (self.average_tries(), self.rolling_average(), self.average_time()) {
+            writeln!(out, "Average tries to win: {:.1}, over the last {} wins: {:.1}", all, ROLLING, latest)?;
+            let seconds = time.as_secs();
+            writeln!(out, "Average time to win: {}:{:02}", seconds / 60, seconds % 60)?;
+
+            // The bars are scaled, so the longest one is BAR characters long
+            writeln!(out, "Tries to win:")?;
+            let histogram = self.histogram();
+            let most = histogram.iter().map(|&(_, games)| games).max().unwrap_or(1);
+            for (tries, games) in histogram {
+                let bar = (games * BAR).div_ceil(most);
+                writeln!(out, "{:>4} | {:<width$} {}", tries, "#".repeat(bar), games, width = BAR)?;
+            }
+        }
+
+        if self.skipped > 0 {
+            writeln!(out, "{} damaged records were skipped", self.skipped)?;
+        }
+        Ok(())
+    }
+
+    // The won games. The "impl Iterator" type lets us return the filtered iterator as it is.
+    fn wins(&self) -> impl Iterator<Item = &Record> {
+        self.records.iter().filter(|record| record.outcome == Outcome::Won)
+    }
+}
+
+// The average of some numbers, or None if there are none
+fn average<I: Iterator<Item = u32>>(numbers: I) -> Option<f64> {
+    let (sum, count) = numbers.fold((0u64, 0u64), |(sum, count), number| (sum + number as u64, count + 1));
+    if count == 0 {
+        None
+    } else {
+        Some(sum as f64 / count as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Outcome, ROLLING, Record, Stats};
+    use game::{Alphabet, Game, GameConfig};
+
+    // Makes the stats of games that have ended one after another. The letters are the outcomes:
+    // w for won, g for gave up and s for solved. Every won game took as many tries as its number.
+    fn stats(outcomes: &str) -> Stats {
+        let mut file = b"cowbull-stats 1\n".to_vec();
+        for (i, outcome) in outcomes.chars().enumerate() {
+            let outcome = match outcome {
+                'w' => "won",
+                'g' => "gave-up",
+                _ => "solved",
+            };
+            file.extend(format!("game {} {} {} 60 4 digits\n", 1_435_400_000 + i, outcome, i + 1).bytes());
+        }
+        Stats::load(&file[..]).unwrap()
+    }
+
+    #[test]
+    fn streaks() {
+        assert_eq!((stats("").current_streak(), stats("").best_streak()), (0, 0));
+        assert_eq!((stats("www").current_streak(), stats("www").best_streak()), (3, 3));
+        assert_eq!((stats("wwwgww").current_streak(), stats("wwwgww").best_streak()), (2, 3));
+        assert_eq!((stats("wwswwwwg").current_streak(), stats("wwswwwwg").best_streak()), (0, 4));
+        assert_eq!((stats("gwgwwg").current_streak(), stats("gwgwwg").best_streak()), (0, 2));
+    }
+
+    #[test]
+    fn rates_and_averages() {
+        let stats = stats("wgwsw");
+        assert_eq!((stats.played(), stats.won()), (5, 3));
+        assert_eq!(stats.win_rate(), Some(0.6));
+        assert_eq!(stats.average_tries(), Some(3.0));
+        assert_eq!(stats.histogram(), [(1, 1), (3, 1), (5, 1)]);
+
+        let empty = Stats::default();
+        assert_eq!((empty.win_rate(), empty.average_tries(), empty.average_time()), (None, None, None));
+    }
+
+    #[test]
+    fn rolling_average_takes_the_latest_wins() {
+        let stats = stats(&"w".repeat(ROLLING + 5));
+        let latest = (6..=ROLLING + 5).sum::<usize>() as f64 / ROLLING as f64;
+        assert_eq!(stats.rolling_average(), Some(latest));
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let config = GameConfig::with_repeats(5, Alphabet::Custom("xyzdigts".chars().collect()), true).unwrap();
+        let mut game = Game::new(config, None);
+        game.hard = true;
+
+        let record = Record::new(&game, Outcome::Solved);
+        let mut file = b"cowbull-stats 1\n".to_vec();
+        record.write(&mut file).unwrap();
+        let stats = Stats::load(&file[..]).unwrap();
+
+        let loaded = &stats.records[0];
+        assert_eq!((loaded.outcome, loaded.tries, loaded.hard, loaded.evil), (Outcome::Solved, 0, true, false));
+        assert_eq!(loaded.config, record.config);
+    }
+
+    #[test]
+    fn damaged_lines_are_skipped() {
+        let text = "cowbull-stats 1\ngame 1435400100 won 5 93 4 digits\ngame 1435400200 won\n\ngame x won 5 93 4 digits\n";
+        let stats = Stats::load(text.as_bytes()).unwrap();
+        assert_eq!((stats.played(), stats.skipped), (1, 2));
+    }
+
+    #[test]
+    fn header_is_checked() {
+        assert!(Stats::load("cowbull-stats 01\n".as_bytes()).is_ok());
+        assert!(Stats::load("cowbull-stats 2\n".as_bytes()).is_err());
+        assert!(Stats::load("cowbull-save 1\n".as_bytes()).is_err());
+    }
+}
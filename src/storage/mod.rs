@@ -0,0 +1,69 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module knows where the game keeps its files between runs. We follow the XDG Base
+//! Directory convention used by most Linux programs: files go to $XDG_DATA_HOME/cowbull, and if
+//! that variable is not set, to ~/.local/share/cowbull.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Returns the directory for our files, creating it if it doesn't exist yet
+pub fn data_dir() -> io::Result<PathBuf> {
+    // env::var_os() returns None if the variable is not set. We also skip empty values, as the
+    // convention tells us to.
+    let base = match env::var_os("XDG_DATA_HOME").filter(|value| !value.is_empty()) {
+        Some(value) => PathBuf::from(value),
+        None => match env::var_os("HOME").filter(|value| !value.is_empty()) {
+            Some(home) => PathBuf::from(home).join(".local").join("share"),
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "neither XDG_DATA_HOME nor HOME is set")),
+        },
+    };
+
+    let dir = base.join("cowbull");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Returns the directory for the files of a player, creating it if needed. Every profile has a
+/// folder of its own under players/, and games played without a profile keep their files right
+/// in the data directory. The name must be checked with profile::valid_name() first.
+pub fn player_dir(player: Option<&str>) -> io::Result<PathBuf> {
+    let dir = match player {
+        Some(name) => data_dir()?.join("players").join(name),
+        None => data_dir()?,
+    };
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// The file where an unfinished game of a player is saved when the player quits
+pub fn autosave_path(player: Option<&str>) -> io::Result<PathBuf> {
+    Ok(player_dir(player)?.join("autosave.cowbull"))
+}
+
+/// The file where the results of every finished game of a player are kept
+pub fn stats_path(player: Option<&str>) -> io::Result<PathBuf> {
+    Ok(player_dir(player)?.join("stats.cowbull"))
+}
+
+/// The folder where the transcripts of every finished game of a player are kept
+pub fn transcripts_dir(player: Option<&str>) -> io::Result<PathBuf> {
+    let dir = player_dir(player)?.join("transcripts");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// The file with the profiles of all players
+pub fn profiles_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join("profiles.cowbull"))
+}
+
+/// The file with the leaderboard of seeded games. It's shared by all players, that's the point.
+pub fn leaderboard_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join("leaderboard.cowbull"))
+}
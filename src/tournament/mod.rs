@@ -0,0 +1,231 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module lets strategies compete. A strategy plays against every secret number the rules
+//! allow, and we count how many tries every game took.
+//!
+//! Playing thousands of games one by one would ask the strategy the same question over and over
+//! again: the first guess is the same in every game, the second one is the same in every game
+//! that got the same first answer, and so on. So instead we play all games at once. We ask for
+//! a guess, split the secrets into groups by the answer they give, and go on with every group
+//! separately. Every group is a set of games that went exactly the same way so far. The results are
+//! the same as if every game was played on its own, as long as the strategy always makes the same
+//! guess for the same history.
+
+use std::time::{Duration, Instant, SystemTime};
+
+use game::{GameConfig, Turn, score};
+use solver::{SolverError, Strategy, all_codes};
+
+/// A game that takes more tries than this is given up, so a strategy that keeps making useless
+/// guesses can't run forever
+pub const MAX_TRIES: usize = 20;
+
+/// How a strategy did in the tournament
+pub struct Report {
+    /// The name of the strategy
+    pub strategy: String,
+    /// How many secrets were guessed in every number of tries: tries[n] is the number of secrets
+    /// guessed in n tries
+    pub tries: Vec<usize>,
+    /// The secrets the strategy couldn't guess: it had nothing to offer, made a guess the rules
+    /// don't allow or ran out of tries
+    pub failed: usize,
+    /// How many different decisions the strategy has made. Games that went the same way share
+    /// their decisions, so this is much less than the number of tries in all games.
+    pub moves: usize,
+    /// How long the strategy was thinking in total
+    pub thinking: Duration,
+    /// The longest move
+    pub longest: Duration,
+}
+
+impl Report {
+
+    /// The number of secrets that were guessed
+    pub fn solved(&self) -> usize {
+        self.tries.iter().sum()
+    }
+
+    /// The average number of tries of the games that were won
+    pub fn mean(&self) -> f64 {
+        let total = self.tries.iter().enumerate().map(|(tries, &count)| tries * count).sum::<usize>();
+        total as f64 / self.solved().max(1) as f64
+    }
+
+    /// The largest number of tries it took to guess a secret
+    pub fn worst(&self) -> usize {
+        self.tries.iter().rposition(|&count| count > 0).unwrap_or(0)
+    }
+
+    /// The average time of a move
+    pub fn time_per_move(&self) -> Duration {
+        self.thinking / self.moves.max(1) as u32
+    }
+}
+
+/// Plays the strategy against every secret number the rules allow. Returns an error if there are
+/// too many of them.
+pub fn run(config: &GameConfig, strategy: &dyn Strategy) -> Result<Report, SolverError> {
+    let space = all_codes(config)?;
+
+    let mut report = Report {
+        strategy: strategy.name().to_string(),
+        tries: vec![0; MAX_TRIES + 1],
+        failed: 0,
+        moves: 0,
+        thinking: Duration::from_secs(0),
+        longest: Duration::from_secs(0),
+    };
+
+    let mut history = Vec::new();
+    play(config, strategy, &space, &space, &mut history, &mut report);
+    Ok(report)
+}
+
+// Plays all games that went like the history so far. The candidates are their secrets. The
+// function calls itself for every group of games that get the same answer to the next guess.
+fn play(config: &GameConfig, strategy: &dyn Strategy, space: &[Vec<u8>], candidates: &[Vec<u8>],
+        history: &mut Vec<Turn>, report: &mut Report) {
+    if history.len() >= MAX_TRIES {
+        report.failed += candidates.len();
+        return;
+    }
+
+    // Ask the strategy and measure how long it thinks. Instant is a clock that only goes forward,
+    // which is what we need to measure time.
+    let started = Instant::now();
+    let guess = strategy.next_guess(config, history, space, candidates);
+    let elapsed = started.elapsed();
+
+    report.moves += 1;
+    report.thinking += elapsed;
+    report.longest = report.longest.max(elapsed);
+
+    // A strategy can have bugs, so we don't trust its guess blindly
+    let guess = match guess {
+        Some(ref guess) if config.is_valid_code(guess) => guess.clone(),
+        _ => {
+            report.failed += candidates.len();
+            return;
+        },
+    };
+
+    // Split the secrets into groups by their answer. An answer of c cows and b bulls goes to
+    // group c * (length + 1) + b, same as in solver::partition().
+    let side = config.length() + 1;
+    let mut groups = vec![Vec::new(); side * side];
+    for code in candidates {
+        let (cows, bulls) = score(code, &guess);
+        groups[cows as usize * side + bulls as usize].push(code.clone());
+    }
+
+    for (index, group) in groups.iter().enumerate() {
+        if group.is_empty() {
+            continue;
+        }
+
+        // All bulls means the guess was the secret, and that game is won
+        let (cows, bulls) = ((index / side) as u8, (index % side) as u8);
+        if bulls as usize == config.length() {
+            report.tries[history.len() + 1] += group.len();
+            continue;
+        }
+
+        history.push(Turn { guess: guess.clone(), cows, bulls, time: SystemTime::now() });
+        play(config, strategy, space, group, history, report);
+        history.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_TRIES, run};
+    use game::{Alphabet, GameConfig, Turn, score};
+    use solver::{Builtin, Solver, Strategy, all_codes, strategies};
+
+    fn small() -> GameConfig {
+        GameConfig::new(3, Alphabet::Custom("abcde".chars().collect())).unwrap()
+    }
+
+    // A strategy that has nothing to say
+    struct Silent;
+
+    impl Strategy for Silent {
+        fn name(&self) -> &str {
+            "silent"
+        }
+
+        fn next_guess(&self, _: &GameConfig, _: &[Turn], _: &[Vec<u8>], _: &[Vec<u8>]) -> Option<Vec<u8>> {
+            None
+        }
+    }
+
+    // A strategy that never learns anything
+    struct Stubborn;
+
+    impl Strategy for Stubborn {
+        fn name(&self) -> &str {
+            "stubborn"
+        }
+
+        fn next_guess(&self, _: &GameConfig, _: &[Turn], space: &[Vec<u8>], _: &[Vec<u8>]) -> Option<Vec<u8>> {
+            Some(space[0].clone())
+        }
+    }
+
+    #[test]
+    fn builtins_solve_everything() {
+        let config = small();
+        for strategy in strategies() {
+            let report = run(&config, &*strategy).unwrap();
+            assert_eq!(report.strategy, strategy.name());
+            assert_eq!(report.failed, 0);
+            assert_eq!(report.solved() as u64, config.space_size());
+            assert_eq!(report.tries[0], 0);
+            assert!(report.worst() < MAX_TRIES);
+        }
+    }
+
+    // Playing all games at once must count the same tries as playing them one by one
+    #[test]
+    fn same_as_playing_alone() {
+        let config = small();
+        let mut tries = vec![0; MAX_TRIES + 1];
+        for secret in all_codes(&config).unwrap() {
+            let mut solver = Solver::new(&config, &Builtin::Minimax).unwrap();
+            let mut count = 1;
+            loop {
+                let guess = solver.next_guess().unwrap();
+                let (cows, bulls) = score(&secret, &guess);
+                if bulls as usize == config.length() {
+                    break;
+                }
+                solver.record(&guess, cows, bulls);
+                count += 1;
+            }
+            tries[count] += 1;
+        }
+        assert_eq!(run(&config, &Builtin::Minimax).unwrap().tries, tries);
+    }
+
+    #[test]
+    fn broken_strategies_fail() {
+        let config = small();
+
+        let report = run(&config, &Silent).unwrap();
+        assert_eq!((report.solved(), report.failed, report.moves), (0, 60, 1));
+
+        // Only the secret it keeps guessing is ever found, and the rest run out of tries
+        let report = run(&config, &Stubborn).unwrap();
+        assert_eq!((report.solved(), report.failed), (1, 59));
+        assert_eq!(report.tries[1], 1);
+    }
+
+    #[test]
+    fn too_many_codes() {
+        assert!(run(&GameConfig::new(10, Alphabet::Letters).unwrap(), &Builtin::First).is_err());
+    }
+}
@@ -0,0 +1,453 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+//! This module writes transcripts of games and plays them back. A transcript has everything
+//! needed to see the game again on another computer: the rules, the seed or the secret number,
+//! every guess with its answer and time, and how the game has ended. It's plain text, so it can be
+//! attached to a bug report or shown in a class:
+//!
+//! ```text
+//! cowbull-transcript 2
+//! rules 4 digits
+//! seed 42
+//! secret 5780
+//! started 1435400000
+//! turn 0123 1 0 1435400012
+//! turn 1456 1 0 1435400031
+//! turn 5780 0 4 1435400057
+//! result won
+//! ```
+//!
+//! Unlike a save file, the secret number is written as it is, and the file is meant to be read by
+//! people. The `rules` line is the same as in a save file, and like there, the `length`, `alphabet`
+//! and `repeats` lines of version 1 are still read. The `hard` and `evil` lines are written when
+//! these modes are on, and there's no `secret` line for the evil host until it has only one number
+//! left. A game that wasn't finished has no `result` line.
+//!
+//! Playing a transcript back means scoring every guess again with `Game::try()` and comparing the
+//! answers with the recorded ones. If the game had a bug, or somebody has changed the file, the
+//! answers won't match, and the replay tells where.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use game::{Alphabet, Game, GameConfig, Guess, Turn, from_unix_time, unix_time};
+use game::format::{HeaderError, check_header, parse_number, parse_rules, rules_words, write_header};
+use game::daily::format_date;
+use stats::Outcome;
+
+// The first word of a transcript
+const MAGIC: &str = "cowbull-transcript";
+
+/// The version of the format we write
+pub const TRANSCRIPT_VERSION: u32 = 2;
+
+// The oldest version we can still read
+const OLDEST_VERSION: u32 = 1;
+
+/// Things that can go wrong while reading a transcript
+#[derive(Debug)]
+pub enum TranscriptError {
+    /// The file can't be read or written
+    Io(io::Error),
+    /// The file was written by a version of the game we can't read
+    Version(u32),
+    /// Something is wrong on a line of the file. Line zero means the whole file.
+    Format(usize, String),
+}
+
+impl fmt::Display for TranscriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TranscriptError::Io(ref error) => write!(f, "{}", error),
+            TranscriptError::Version(version) =>
+                write!(f, "Transcript format version {} is not supported, version {} is the latest", version, TRANSCRIPT_VERSION),
+            TranscriptError::Format(0, ref message) => write!(f, "Bad transcript: {}", message),
+            TranscriptError::Format(line, ref message) => write!(f, "Bad transcript, line {}: {}", line, message),
+        }
+    }
+}
+
+impl From<io::Error> for TranscriptError {
+    fn from(error: io::Error) -> TranscriptError {
+        TranscriptError::Io(error)
+    }
+}
+
+/// Everything that has happened in a game
+#[derive(Clone, Debug)]
+pub struct Transcript {
+    /// The rules of the game
+    pub config: GameConfig,
+    /// The seed the secret number was made from, if any
+    pub seed: Option<u64>,
+    /// The secret number, if the game has one
+    pub secret: Option<Vec<u8>>,
+    /// True if the game was played in hard mode
+    pub hard: bool,
+    /// True if the game was played against the evil host
+    pub evil: bool,
+    /// The moment the game has started
+    pub started: SystemTime,
+    /// Every guess with its answer
+    pub turns: Vec<Turn>,
+    /// How the game has ended, or None if it goes on
+    pub outcome: Option<Outcome>,
+}
+
+impl Transcript {
+
+    /// Makes a transcript of a game
+    pub fn from_game(game: &Game, outcome: Option<Outcome>) -> Transcript {
+        Transcript {
+            config: game.config.clone(),
+            seed: game.seed,
+            secret: game.reveal().map(|secret| secret.to_vec()),
+            hard: game.hard,
+            evil: game.is_evil(),
+            started: game.started,
+            turns: game.history.clone(),
+            outcome,
+        }
+    }
+
+    /// Writes the transcript
+    pub fn write<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write_header(out, MAGIC, TRANSCRIPT_VERSION)?;
+        writeln!(out, "rules {}", rules_words(&self.config))?;
+        for &(on, flag) in &[(self.hard, "hard"), (self.evil, "evil")] {
+            if on {
+                writeln!(out, "{} yes", flag)?;
+            }
+        }
+        if let Some(seed) = self.seed {
+            writeln!(out, "seed {}", seed)?;
+        }
+        if let Some(ref secret) = self.secret {
+            writeln!(out, "secret {}", self.config.format_code(secret))?;
+        }
+        writeln!(out, "started {}", unix_time(self.started))?;
+        for turn in &self.turns {
+            writeln!(out, "turn {} {} {} {}", self.config.format_code(&turn.guess), turn.cows, turn.bulls, unix_time(turn.time))?;
+        }
+        if let Some(outcome) = self.outcome {
+            writeln!(out, "result {}", outcome.name())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a transcript. The answers are not checked here, that's what replay() is for, but
+    /// every guess and the secret number must be valid for the rules.
+    pub fn load<R: BufRead>(input: R) -> Result<Transcript, TranscriptError> {
+        let mut version = TRANSCRIPT_VERSION;
+        let mut config = None;
+        // Version 1 had the rules on three lines
+        let mut length = None;
+        let mut alphabet = None;
+        let mut flags = Vec::new();
+        let mut seed = None;
+        let mut secret = None;
+        let mut started = None;
+        let mut turns = Vec::new();
+        let mut outcome = None;
+
+        for (i, line) in input.lines().enumerate() {
+            let line = line?;
+            let number = i + 1;
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+
+            if number == 1 {
+                match check_header(&line, MAGIC, OLDEST_VERSION..=TRANSCRIPT_VERSION) {
+                    Ok(found) => {
+                        version = found;
+                        continue;
+                    },
+                    Err(HeaderError::Version(version)) => return Err(TranscriptError::Version(version)),
+                    Err(HeaderError::BadVersion) => return Err(format_error(number, "bad version")),
+                    Err(HeaderError::Foreign) => return Err(format_error(number, "this is not a transcript")),
+                }
+            }
+
+            match words.as_slice() {
+                [] => (),
+                ["rules", length, alphabet, flags @ ..] if version > 1 && flags.iter().all(|&flag| flag == "repeats") =>
+                    config = Some(parse_rules(length, alphabet, flags).map_err(|message| format_error(number, &message))?),
+                ["length", value] if version == 1 => length = Some(parse(number, value)?),
+                ["alphabet", "custom", symbols] if version == 1 => alphabet = Some(Alphabet::Custom(symbols.chars().collect())),
+                ["alphabet", name] if version == 1 => alphabet = Some(Alphabet::from_name(name)),
+                [flag @ ("hard" | "evil"), "yes"] => flags.push(flag.to_string()),
+                ["repeats", "yes"] if version == 1 => flags.push("repeats".to_string()),
+                ["seed", value] => seed = Some(parse(number, value)?),
+                ["secret", value] => secret = Some((number, value.to_string())),
+                ["started", value] => started = Some(parse(number, value)?),
+                ["turn", guess, cows, bulls, time] =>
+                    turns.push((number, guess.to_string(), parse(number, cows)?, parse(number, bulls)?, parse(number, time)?)),
+                ["result", name] => outcome = Some(Outcome::from_name(name).ok_or_else(|| format_error(number, "unknown result"))?),
+                _ => return Err(format_error(number, "unknown record")),
+            }
+        }
+
+        let started = started.ok_or_else(|| format_error(0, "start time is missing"))?;
+        let flag = |name: &str| flags.iter().any(|flag| flag == name);
+        let config = match version {
+            1 => {
+                let length = length.ok_or_else(|| format_error(0, "code length is missing"))?;
+                let alphabet = alphabet.ok_or_else(|| format_error(0, "alphabet is missing"))?;
+                GameConfig::with_repeats(length, alphabet, flag("repeats")).map_err(|error| format_error(0, &error.to_string()))?
+            },
+            _ => config.ok_or_else(|| format_error(0, "rules are missing"))?,
+        };
+
+        // Codes are written with symbols, so we turn them back into indices and check them
+        let code = |line: usize, value: &str| value.chars()
+            .map(|symbol| config.index_of(symbol))
+            .collect::<Option<Vec<u8>>>()
+            .filter(|code| config.is_valid_code(code))
+            .ok_or_else(|| format_error(line, &format!("\"{}\" is not a code of these rules", value)));
+
+        let secret = match secret {
+            Some((line, ref value)) => Some(code(line, value)?),
+            None => None,
+        };
+        let turns = turns.into_iter()
+            .map(|(line, guess, cows, bulls, time)| Ok(Turn { guess: code(line, &guess)?, cows, bulls, time: from_unix_time(time) }))
+            .collect::<Result<Vec<Turn>, TranscriptError>>()?;
+
+        Ok(Transcript { config, seed, secret, hard: flag("hard"), evil: flag("evil"), started: from_unix_time(started), turns, outcome })
+    }
+
+    /// Writes the transcript to a file
+    pub fn save_file(&self, path: &Path) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        self.write(&mut file)
+    }
+
+    /// Reads a transcript from a file
+    pub fn load_file(path: &Path) -> Result<Transcript, TranscriptError> {
+        Transcript::load(BufReader::new(File::open(path)?))
+    }
+
+    /// Starts playing the transcript back. The game is made from the secret number if there is
+    /// one, from the seed otherwise, or with the evil host. Every answer of the evil host agrees
+    /// with the number it's left with, so once it has one, we play against that number like
+    /// against any other. If the transcript has both a seed and a secret, they must agree.
+    pub fn replay(&self) -> Result<Replay<'_>, TranscriptError> {
+        let mut game = match (self.secret.as_ref(), self.seed, self.evil) {
+            (None, _, true) => Game::evil(self.config.clone()).map_err(|error| format_error(0, &error.to_string()))?,
+            (Some(secret), _, _) => {
+                if let Some(seed) = self.seed {
+                    if Game::new(self.config.clone(), Some(seed)).reveal() != Some(secret.as_slice()) {
+                        return Err(format_error(0, "the secret doesn't match the seed"));
+                    }
+                }
+                // The secret was checked by load(), so it's a valid guess
+                let secret = Guess::from_code(secret.clone(), &self.config).ok_or_else(|| format_error(0, "bad secret"))?;
+                Game::with_secret(self.config.clone(), &secret)
+            },
+            (None, Some(seed), false) => Game::new(self.config.clone(), Some(seed)),
+            (None, None, false) => return Err(format_error(0, "neither a seed nor a secret is given")),
+        };
+        game.hard = self.hard;
+        game.seed = self.seed;
+        game.started = self.started;
+        Ok(Replay { transcript: self, game })
+    }
+}
+
+/// A recorded answer that the game gives differently now
+#[derive(Debug)]
+pub struct Mismatch {
+    /// The number of the turn, counting from one
+    pub turn: usize,
+    /// The cows and bulls in the transcript
+    pub recorded: (u8, u8),
+    /// The cows and bulls the game gives
+    pub actual: (u8, u8),
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Turn {} was recorded as {} cows and {} bulls, but the game says {} cows and {} bulls",
+               self.turn, self.recorded.0, self.recorded.1, self.actual.0, self.actual.1)
+    }
+}
+
+/// A transcript being played back turn by turn
+pub struct Replay<'a> {
+    transcript: &'a Transcript,
+    game: Game,
+}
+
+impl<'a> Replay<'a> {
+
+    /// The game as it is after the turns played so far. Its hint table is up to date.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// True if every turn has been played
+    pub fn is_over(&self) -> bool {
+        self.game.history.len() == self.transcript.turns.len()
+    }
+
+    /// Plays the next turn. Returns None when there are no more turns, or the recorded turn with
+    /// the check of its answer. A mismatch doesn't stop the replay, the rest of the game is still
+    /// worth a look.
+    pub fn step(&mut self) -> Option<(&'a Turn, Result<(), Mismatch>)> {
+        let turn = self.transcript.turns.get(self.game.history.len())?;
+        let guess = Guess::from_code(turn.guess.clone(), &self.game.config)?;
+
+        let actual = self.game.try(&guess);
+        self.game.analyze();
+
+        // The recorded time is kept, so the replayed history looks like the real one
+        if let Some(last) = self.game.history.last_mut() {
+            last.time = turn.time;
+        }
+
+        let recorded = (turn.cows, turn.bulls);
+        if actual == recorded {
+            Some((turn, Ok(())))
+        } else {
+            Some((turn, Err(Mismatch { turn: self.game.history.len(), recorded, actual })))
+        }
+    }
+
+    /// Checks the recorded result against the replayed game once every turn is played. A game
+    /// recorded as won must end with a winning guess, and any other game must not.
+    pub fn check_result(&self) -> Result<(), String> {
+        let won = self.game.history.last().is_some_and(|turn| turn.bulls as usize == self.game.config.length());
+        match self.transcript.outcome {
+            Some(Outcome::Won) if !won => Err("The game was recorded as won, but the last guess doesn't win".to_string()),
+            Some(Outcome::GaveUp) if won => Err("The game was recorded as given up, but the last guess wins".to_string()),
+            None if won => Err("The game was recorded as unfinished, but the last guess wins".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Writes the transcript of a game into a folder. The file is named after the moment the game
+/// has started, like 2015-06-27-1435400000.cowbull, so the files sort by time. Two games can start
+/// in the same second, so if the name is taken, we add a number to it. Returns the path of the
+/// file.
+pub fn save_to_dir(dir: &Path, transcript: &Transcript) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let name = format!("{}-{}", format_date(transcript.started), unix_time(transcript.started));
+    for number in 1.. {
+        let path = match number {
+            1 => dir.join(format!("{}.cowbull", name)),
+            _ => dir.join(format!("{}-{}.cowbull", name, number)),
+        };
+
+        // create_new() fails if the file is already there, so we never overwrite another game
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                transcript.write(&mut file)?;
+                return Ok(path);
+            },
+            Err(ref error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    unreachable!("there's always a free name")
+}
+
+fn format_error(line: usize, message: &str) -> TranscriptError {
+    TranscriptError::Format(line, message.to_string())
+}
+
+fn parse<T: ::std::str::FromStr>(line: usize, value: &str) -> Result<T, TranscriptError> {
+    parse_number(value).map_err(|message| format_error(line, &message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Transcript, TranscriptError};
+    use game::{Alphabet, Game, GameConfig, Guess, unix_time};
+    use stats::Outcome;
+
+    // A game of 5780 won in two tries
+    fn transcript() -> Transcript {
+        let mut game = Game::new(GameConfig::default(), Some(42));
+        game.hard = true;
+        for guess in &["0123", "5780"] {
+            game.play(&guess.parse::<Guess>().unwrap());
+        }
+        Transcript::from_game(&game, Some(Outcome::Won))
+    }
+
+    fn text(transcript: &Transcript) -> String {
+        let mut file = Vec::new();
+        transcript.write(&mut file).unwrap();
+        String::from_utf8(file).unwrap()
+    }
+
+    fn load(text: &str) -> Result<Transcript, TranscriptError> {
+        Transcript::load(text.as_bytes())
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = transcript();
+        let loaded = load(&text(&original)).unwrap();
+        assert_eq!(loaded.config, original.config);
+        assert_eq!((loaded.seed, loaded.secret.as_ref()), (Some(42), Some(&vec![5, 7, 8, 0])));
+        assert_eq!((loaded.hard, loaded.evil, loaded.outcome), (true, false, Some(Outcome::Won)));
+        assert_eq!(unix_time(loaded.started), unix_time(original.started));
+        assert_eq!(loaded.turns.len(), 2);
+        for (loaded, original) in loaded.turns.iter().zip(&original.turns) {
+            assert_eq!((&loaded.guess, loaded.cows, loaded.bulls), (&original.guess, original.cows, original.bulls));
+            assert_eq!(unix_time(loaded.time), unix_time(original.time));
+        }
+
+        let mut replay = loaded.replay().unwrap();
+        while let Some((_, check)) = replay.step() {
+            assert!(check.is_ok());
+        }
+        assert!(replay.is_over());
+        assert_eq!(replay.check_result(), Ok(()));
+    }
+
+    // A changed answer is found, and the replay goes on after it
+    #[test]
+    fn mismatch() {
+        let changed = text(&transcript()).replace("turn 0123 1 0", "turn 0123 0 1");
+        let transcript = load(&changed).unwrap();
+        let mut replay = transcript.replay().unwrap();
+
+        let mismatch = replay.step().unwrap().1.unwrap_err();
+        assert_eq!((mismatch.turn, mismatch.recorded, mismatch.actual), (1, (0, 1), (1, 0)));
+        assert!(replay.step().unwrap().1.is_ok());
+        assert!(replay.step().is_none());
+    }
+
+    #[test]
+    fn wrong_result_or_secret() {
+        let given_up = load(&text(&transcript()).replace("result won", "result gave-up")).unwrap();
+        let mut replay = given_up.replay().unwrap();
+        while replay.step().is_some() {}
+        assert!(replay.check_result().is_err());
+
+        let wrong_secret = text(&transcript()).replace("secret 5780", "secret 5781");
+        assert!(load(&wrong_secret).unwrap().replay().is_err());
+    }
+
+    #[test]
+    fn version_one_is_still_read() {
+        let file = "cowbull-transcript 1\nlength 3\nalphabet custom abc\nrepeats yes\nsecret aab\n\
+                    started 1435400000\nturn abc 1 1 1435400012\n";
+        let transcript = load(file).unwrap();
+        assert_eq!(transcript.config, GameConfig::with_repeats(3, Alphabet::Custom("abc".chars().collect()), true).unwrap());
+        assert_eq!(transcript.secret, Some(vec![0, 0, 1]));
+        assert_eq!(transcript.outcome, None);
+        assert!(transcript.replay().unwrap().step().unwrap().1.is_ok());
+
+        // Version 2 only knows the rules line
+        assert!(load(&file.replace("transcript 1", "transcript 2")).is_err());
+    }
+}
@@ -0,0 +1,548 @@
+/*
+    Copyright 2015 Andrei "dread.deimos" Arsenin
+    See LICENSE file for legal stuff
+*/
+
+// This module draws the game on the whole terminal screen instead of printing line after line.
+// The history of guesses is on the left, the hint table is on the right and is always up to date,
+// the guess is typed into an input line that checks it on every key, and a status bar at the
+// bottom shows the tries and the time. The player can also put their own notes on the cells of
+// the hint table, with the arrow keys or by clicking them.
+//
+// We don't use any library for this. Terminals understand escape sequences: special strings that
+// start with the ESC character and move the cursor, change colors or clear the screen. We also
+// need the terminal to give us every key as soon as it's pressed, instead of waiting for Enter,
+// which is called the raw mode. The stty program can switch the terminal to it and back.
+
+use std::io::{self, stdin, stdout, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use cowbull::game::{GameConfig, Guess, GuessError, Hint};
+
+use session::Session;
+
+// The column where the hint table starts. The history is on the left of it.
+const GRID_COLUMN: usize = 40;
+// The row of the hint table header, counting from one like the terminal does
+const GRID_ROW: usize = 3;
+// Every row of the table starts with the symbol and a colon, and every cell is three characters
+// wide with the mark in the middle
+const LABEL: usize = 2;
+const CELL: usize = 3;
+
+// How long we wait for the rest of an escape sequence. The terminal sends a sequence at once, but
+// it can still come in two reads, and an ESC without anything after it is the Escape key.
+const ESC_WAIT: Duration = Duration::from_millis(50);
+
+// The escape sequences we use. "\x1b[" starts most of them, and the letter at the end tells
+// what to do.
+const CLEAR: &str = "\x1b[2J";
+const RESET: &str = "\x1b[0m";
+const REVERSE: &str = "\x1b[7m";
+const DIM: &str = "\x1b[2m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const NOTE: &str = "\x1b[1;36m";
+
+// A note the player can put on a cell of the hint table. It's shown instead of the computed hint.
+#[derive(PartialEq, Copy, Clone)]
+enum Note {
+    Here,
+    Maybe,
+    NotHere,
+}
+
+// Everything the player can do with the keyboard and the mouse
+enum Event {
+    Char(char),
+    Backspace,
+    Enter,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    // A click at a column and a row of the screen
+    Click(usize, usize),
+    Restart,
+    Quit,
+}
+
+// Everything on the screen that is not a part of the game
+struct Screen<'a> {
+    session: &'a mut Session,
+    // What the player has typed so far
+    input: String,
+    // A line of text under the input, and its color
+    message: String,
+    color: &'static str,
+    // The notes of the player, one line per symbol and one column per position like the hints
+    notes: Vec<Vec<Option<Note>>>,
+    // The selected cell of the hint table: a symbol and a position
+    cursor: (usize, usize),
+    // The first symbol of the hint table on the screen. A long alphabet doesn't fit, so the table
+    // scrolls to keep the cursor in sight.
+    top: usize,
+    // When true, the arrow keys move over the hint table instead of editing the input
+    grid: bool,
+    rows: usize,
+}
+
+// Plays the games of the session on the full screen until the player quits. Fails if the input is
+// not a terminal.
+pub fn play(session: &mut Session) -> io::Result<()> {
+    // The raw mode is turned off when the raw variable goes out of scope, whichever way we leave
+    let raw = RawMode::start()?;
+
+    // Reading a key waits until a key is pressed, but the clock must keep ticking. So keys are
+    // read by another thread and sent to us through a channel, and we wait for them with a timeout.
+    let (sender, keys) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0; 64];
+        while let Ok(count) = stdin().read(&mut buffer) {
+            if count == 0 || sender.send(buffer[..count].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    let notes = blank_notes(&session.game.config);
+    let mut screen = Screen {
+        session,
+        input: String::new(),
+        message: String::new(),
+        color: RESET,
+        notes,
+        cursor: (0, 0),
+        top: 0,
+        grid: false,
+        rows: raw.rows,
+    };
+    screen.check_input();
+
+    loop {
+        // A game that has just ended may have failed to get into the stats file or the leaderboard
+        if let Some(error) = screen.session.record_error() {
+            screen.say(RED, format!("Can't record the game: {}", error));
+        }
+
+        screen.draw()?;
+        for event in next_events(&keys) {
+            match event {
+                Event::Quit => {
+                    // The game is saved to be resumed, or recorded as given up if it can't be
+                    if screen.session.game.tries > 0 && ::try_autosave(screen.session).is_err() {
+                        screen.session.finish();
+                    }
+                    return Ok(());
+                },
+                event => screen.handle(event),
+            }
+        }
+    }
+}
+
+impl<'a> Screen<'a> {
+
+    // Changes the screen according to an event
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Tab => self.grid = !self.grid,
+            Event::Restart => {
+                if self.session.game.tries > 0 {
+                    ::forget_autosave(self.session);
+                }
+                self.session.restart();
+                self.new_game("A new game has started");
+            },
+
+            // In the hint table the keys move the cursor and put notes on cells
+            Event::Up if self.grid => self.cursor.0 = self.cursor.0.saturating_sub(1),
+            Event::Down if self.grid => self.cursor.0 = (self.cursor.0 + 1).min(self.notes.len() - 1),
+            Event::Left if self.grid => self.cursor.1 = self.cursor.1.saturating_sub(1),
+            Event::Right if self.grid => self.cursor.1 = (self.cursor.1 + 1).min(self.session.game.config.length() - 1),
+            Event::Char(' ') | Event::Enter if self.grid => self.toggle(self.cursor),
+            Event::Click(column, row) => if let Some(cell) = self.cell_at(column, row) {
+                self.cursor = cell;
+                self.toggle(cell);
+            },
+
+            // Everything else edits the guess
+            Event::Char(c) => {
+                self.input.push(c);
+                self.check_input();
+            },
+            Event::Backspace => {
+                self.input.pop();
+                self.check_input();
+            },
+            Event::Enter => self.submit(),
+            Event::Up | Event::Down | Event::Left | Event::Right | Event::Quit => (),
+        }
+        self.scroll();
+    }
+
+    // How many lines of the hint table fit on the screen. The lines at the bottom are taken by the
+    // input, the message and the status bar.
+    fn room(&self) -> usize {
+        self.rows.saturating_sub(GRID_ROW + 3)
+    }
+
+    // Scrolls the hint table, so the cursor is never on a line that isn't shown
+    fn scroll(&mut self) {
+        let room = self.room().max(1);
+        if self.cursor.0 < self.top {
+            self.top = self.cursor.0;
+        } else if self.cursor.0 >= self.top + room {
+            self.top = self.cursor.0 + 1 - room;
+        }
+    }
+
+    // Checks the input as it's typed and tells the player what's wrong with it, or that it's fine
+    fn check_input(&mut self) {
+        match validate(self.session, &self.input) {
+            Ok(Some(_)) => self.say(GREEN, "Press Enter to guess".to_string()),
+            Ok(None) => {
+                let missing = self.session.game.config.length() - self.input.chars().count();
+                self.say(DIM, format!("{} more to type", missing));
+            },
+            Err(message) => self.say(RED, message),
+        }
+    }
+
+    // Scores the guess if it's valid
+    fn submit(&mut self) {
+        let guess = match validate(self.session, &self.input) {
+            Ok(Some(guess)) => guess,
+            _ => return,
+        };
+        self.input.clear();
+
+        let game = &mut self.session.game;
+        let (cows, bulls, won) = game.play(&guess);
+        if won {
+            let tries = game.tries;
+            ::forget_autosave(self.session);
+            self.session.win();
+            self.new_game(&format!("You won in {} tries! A new number is ready", tries));
+            return;
+        }
+
+        game.analyze();
+        let saved = ::try_autosave(self.session);
+
+        match saved {
+            Err(error) => self.say(RED, format!("Can't auto-save the game: {}", error)),
+            Ok(()) if cows == 0 && bulls == 0 => self.say(RESET, "Nothing found".to_string()),
+            Ok(()) => self.say(RESET, format!("Found {} cows and {} bulls", cows, bulls)),
+        }
+    }
+
+    // Forgets the notes of the old game
+    fn new_game(&mut self, message: &str) {
+        self.notes = blank_notes(&self.session.game.config);
+        self.input.clear();
+        self.say(GREEN, message.to_string());
+    }
+
+    fn say(&mut self, color: &'static str, message: String) {
+        self.color = color;
+        self.message = message;
+    }
+
+    // Puts the next note on a cell: here, maybe, not here and then nothing again
+    fn toggle(&mut self, (symbol, position): (usize, usize)) {
+        let note = &mut self.notes[symbol][position];
+        *note = match *note {
+            None => Some(Note::Here),
+            Some(Note::Here) => Some(Note::Maybe),
+            Some(Note::Maybe) => Some(Note::NotHere),
+            Some(Note::NotHere) => None,
+        };
+    }
+
+    // Finds the cell of the hint table at a column and a row of the screen. The table starts at
+    // GRID_COLUMN, so its first cell starts right after the label.
+    fn cell_at(&self, column: usize, row: usize) -> Option<(usize, usize)> {
+        let line = row.checked_sub(GRID_ROW + 1)?;
+        let symbol = self.top + line;
+        let position = column.checked_sub(GRID_COLUMN + LABEL)? / CELL;
+        if line < self.room() && symbol < self.notes.len() && position < self.session.game.config.length() {
+            Some((symbol, position))
+        } else {
+            None
+        }
+    }
+
+    // Draws the whole screen. It's simpler than keeping track of what has changed, and the screen
+    // is small enough to draw it many times a second.
+    fn draw(&self) -> io::Result<()> {
+        let game = &self.session.game;
+        let config = &game.config;
+
+        // We put everything into one string and write it at once, so the screen doesn't flicker
+        let mut out = String::new();
+        out.push_str(CLEAR);
+
+        let mut title = format!("Cows and Bulls: guess the number of {}", config.describe());
+        if game.hard {
+            title.push_str(", hard mode");
+        }
+        if game.is_evil() {
+            title.push_str(", evil mode");
+        }
+        put(&mut out, 1, 1, &title);
+
+        // The history on the left. The lines at the bottom of the screen are taken, so if the
+        // history is too long, we show its end.
+        let room = self.room();
+        let history = ::history_lines(game);
+        let skip = history.len().saturating_sub(room).max(1);
+        put(&mut out, GRID_ROW, 1, &history[0]);
+        for (i, line) in history.iter().skip(skip).enumerate() {
+            put(&mut out, GRID_ROW + 1 + i, 1, line);
+        }
+
+        // The hint table on the right, with the player's notes on top of the computed hints. The
+        // numbers of the positions are centered in their cells, right above the marks. When the
+        // table doesn't fit, the label column of the header tells that it scrolls.
+        let hidden = self.top > 0 || self.top + room < game.hint_table.len();
+        let mut header = if hidden { format!("{}^v{}", DIM, RESET) } else { " ".repeat(LABEL) };
+        for position in 0..config.length() {
+            header.push_str(&format!("{:^1$}", position + 1, CELL));
+        }
+        put(&mut out, GRID_ROW, GRID_COLUMN, &header);
+        for (symbol, hints) in game.hint_table.iter().enumerate().skip(self.top).take(room) {
+            let mut line = format!("{}:", config.symbol(symbol as u8));
+            for (position, hint) in hints.iter().enumerate() {
+                let (color, mark) = match (self.notes[symbol][position], *hint) {
+                    (Some(Note::Here), _) => (NOTE, '+'),
+                    (Some(Note::Maybe), _) => (NOTE, '?'),
+                    (Some(Note::NotHere), _) => (NOTE, '-'),
+                    (None, Hint::Here) => (GREEN, '+'),
+                    (None, Hint::Maybe(_)) => (YELLOW, '?'),
+                    (None, Hint::NotHere) => (RED, '-'),
+                    (None, Hint::Unknown) => (DIM, '.'),
+                };
+                let selected = if self.grid && self.cursor == (symbol, position) { REVERSE } else { "" };
+                line.push_str(&format!(" {}{}{}{} ", color, selected, mark, RESET));
+            }
+            put(&mut out, GRID_ROW + 1 + symbol - self.top, GRID_COLUMN, &line);
+        }
+
+        // The legend goes under the table if there's room, or else it's left out
+        let legend_row = GRID_ROW + 2 + config.symbols().len().min(room);
+        if legend_row + 2 < self.rows {
+            let legend = format!("{}+{} here {}?{} maybe {}-{} not here {}+?-{} your notes",
+                                 GREEN, RESET, YELLOW, RESET, RED, RESET, NOTE, RESET);
+            put(&mut out, legend_row, GRID_COLUMN, &legend);
+        }
+
+        // The message and the status bar at the bottom
+        put(&mut out, self.rows - 1, 1, &format!("{}{}{}", self.color, self.message, RESET));
+        let seconds = SystemTime::now().duration_since(game.started).map(|time| time.as_secs()).unwrap_or(0);
+        let status = format!(" Tries: {} | Time: {}:{:02} | {} | Ctrl+R: new game | Ctrl+C: quit ",
+                             game.tries, seconds / 60, seconds % 60,
+                             if self.grid { "Tab: back to guessing, arrows and Space or click: notes" } else { "Tab: notes" });
+        put(&mut out, self.rows, 1, &format!("{}{}{}", REVERSE, status, RESET));
+
+        // The input line, with the cursor left at its end unless the table has the focus
+        let prompt = format!("> {}", self.input);
+        put(&mut out, self.rows - 2, 1, &prompt);
+        if self.grid {
+            out.push_str("\x1b[?25l");
+        } else {
+            out.push_str(&format!("\x1b[?25h\x1b[{};{}H", self.rows - 2, prompt.chars().count() + 1));
+        }
+
+        let stdout = stdout();
+        let mut stdout = stdout.lock();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+// Checks a guess that is being typed. Returns the guess if it's complete and valid, None if it's
+// fine so far but not complete, or a message that tells what's wrong.
+fn validate(session: &Session, input: &str) -> Result<Option<Guess>, String> {
+    let game = &session.game;
+    match Guess::parse(input, &game.config) {
+        Ok(guess) => match game.check_hard(&guess) {
+            Ok(()) => Ok(Some(guess)),
+            Err(contradiction) => Err(contradiction.to_string()),
+        },
+
+        // A short guess is not an error yet, unless it already repeats a symbol. The parser only
+        // looks for repeats in guesses of the right length, so we look for them ourselves.
+        Err(GuessError::WrongLength(expected, found)) if found < expected => {
+            let repeated = input.chars().enumerate().find(|&(i, c)| input.chars().skip(i + 1).any(|other| other == c));
+            match repeated {
+                Some((_, c)) if !game.config.repeats() => Err(GuessError::repeated(c, &game.config).to_string()),
+                _ => Ok(None),
+            }
+        },
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+fn blank_notes(config: &GameConfig) -> Vec<Vec<Option<Note>>> {
+    vec![vec![None; config.length()]; config.symbols().len()]
+}
+
+// Moves the cursor to a row and a column and writes the text there
+fn put(out: &mut String, row: usize, column: usize, text: &str) {
+    out.push_str(&format!("\x1b[{};{}H{}", row, column, text));
+}
+
+// Waits for keys and turns them into events. If nothing is pressed for a while, we return no
+// events, so the screen is drawn again and the clock moves.
+fn next_events(keys: &Receiver<Vec<u8>>) -> Vec<Event> {
+    let mut bytes = match keys.recv_timeout(Duration::from_millis(500)) {
+        Ok(bytes) => bytes,
+        Err(RecvTimeoutError::Timeout) => return Vec::new(),
+        // The input is over, there's nobody to play with
+        Err(RecvTimeoutError::Disconnected) => return vec![Event::Quit],
+    };
+
+    // If the bytes end in the middle of an escape sequence, the rest of it is probably on its way.
+    // If it doesn't come soon, the player has pressed the Escape key.
+    while unfinished(&bytes) {
+        match keys.recv_timeout(ESC_WAIT) {
+            Ok(more) => bytes.extend(more),
+            Err(_) => break,
+        }
+    }
+
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    let mut chars = text.chars().peekable();
+    let mut events = Vec::new();
+
+    while let Some(c) = chars.next() {
+        let event = match c {
+            '\x03' | '\x11' => Event::Quit,
+            '\x12' => Event::Restart,
+            '\r' | '\n' => Event::Enter,
+            '\t' => Event::Tab,
+            '\x7f' | '\x08' => Event::Backspace,
+
+            // The arrow keys send ESC [ and a letter. A mouse click sends ESC [ < followed by the
+            // button, the column and the row separated by semicolons, and M when it's pressed.
+            // Other keys send their own numbers before the letter, and we skip them whole.
+            '\x1b' if chars.peek() == Some(&'[') => {
+                chars.next();
+                let mut parameters = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !is_parameter(c) {
+                        break;
+                    }
+                    parameters.push(c);
+                    chars.next();
+                }
+                match (parameters.as_str(), chars.next()) {
+                    ("", Some('A')) => Event::Up,
+                    ("", Some('B')) => Event::Down,
+                    ("", Some('C')) => Event::Right,
+                    ("", Some('D')) => Event::Left,
+                    (parameters, Some('M')) if parameters.starts_with('<') => {
+                        let numbers = parameters[1..].split(';').map(|number| number.parse::<usize>().unwrap_or(0)).collect::<Vec<usize>>();
+                        match numbers.as_slice() {
+                            [0, column, row] => Event::Click(*column, *row),
+                            _ => continue,
+                        }
+                    },
+                    _ => continue,
+                }
+            },
+            // The Escape key alone quits too. We've waited for the rest of a sequence above, so
+            // this one really is alone.
+            '\x1b' => Event::Quit,
+
+            c if !c.is_control() => Event::Char(c),
+            _ => continue,
+        };
+        events.push(event);
+    }
+
+    events
+}
+
+// The characters between ESC [ and the letter that ends a sequence: digits, semicolons and a few
+// more, like the < of a mouse click
+fn is_parameter(c: char) -> bool {
+    ('0'..='?').contains(&c)
+}
+
+// True if the bytes end with the start of an escape sequence: ESC alone, or ESC [ followed by
+// nothing but parameters
+fn unfinished(bytes: &[u8]) -> bool {
+    match bytes.iter().rposition(|&byte| byte == 0x1b) {
+        Some(start) => match &bytes[start + 1..] {
+            [] => true,
+            [b'[', rest @ ..] => rest.iter().all(|&byte| is_parameter(byte as char)),
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+// The terminal in the raw mode. The mode is turned off when the value is dropped, so the terminal
+// is fixed even if we leave because of an error or a panic.
+struct RawMode {
+    // The settings of the terminal before we've changed them, as stty has printed them
+    saved: String,
+    rows: usize,
+}
+
+impl RawMode {
+    fn start() -> io::Result<RawMode> {
+        let saved = stty(&["-g"])?;
+        let saved = saved.trim().to_string();
+
+        // We need a few rows for the table, the input and the status bar. Some terminals don't
+        // know their size and say zero, and then we guess the classic 24 rows.
+        let rows = stty(&["size"])?
+            .split_whitespace()
+            .next()
+            .and_then(|rows| rows.parse::<usize>().ok())
+            .filter(|&rows| rows > 0)
+            .unwrap_or(24)
+            .max(GRID_ROW + 6);
+
+        stty(&["raw", "-echo"])?;
+
+        // Switch to the alternate screen, so the old text of the terminal comes back when we're
+        // done, and ask for mouse clicks
+        let mut stdout = stdout();
+        stdout.write_all(b"\x1b[?1049h\x1b[?1000h\x1b[?1006h")?;
+        stdout.flush()?;
+
+        Ok(RawMode { saved, rows })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let mut stdout = stdout();
+        let _ = stdout.write_all(b"\x1b[?1006l\x1b[?1000l\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+// Runs stty with the terminal as its input and returns what it has printed. It fails if the
+// input is not a terminal, and then there's no full screen for us.
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("the full-screen mode needs a terminal"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}